@@ -0,0 +1,70 @@
+use crate::DatabaseType;
+#[cfg(any(feature = "kv-websocket", feature = "http", feature = "kv-tikv"))]
+use std::net::{IpAddr, Ipv6Addr};
+
+impl DatabaseType {
+    /// The port used when the settings leave it at zero: the standard port of the scheme for
+    /// WebSocket and HTTP, and the placement driver port for TiKV.
+    pub fn default_port(&self) -> Option<u16> {
+        match *self {
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocket => Some(80),
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocketSecure => Some(443),
+            #[cfg(feature = "http")]
+            DatabaseType::Http => Some(80),
+            #[cfg(feature = "http")]
+            DatabaseType::Https => Some(443),
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => Some(2379),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// Whether the host is an IPv4 address, an IPv6 address with or without brackets, or a hostname
+/// made of dot separated labels of letters, digits and hyphens.
+#[cfg(any(feature = "kv-websocket", feature = "http", feature = "kv-tikv"))]
+pub(crate) fn is_valid_host(host: &str) -> bool {
+    if let Some(ip) = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        return ip.parse::<Ipv6Addr>().is_ok();
+    }
+    host.parse::<IpAddr>().is_ok() || is_valid_hostname(host)
+}
+
+#[cfg(any(feature = "kv-websocket", feature = "http", feature = "kv-tikv"))]
+fn is_valid_hostname(host: &str) -> bool {
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.is_empty() || name.len() > 253 {
+        return false;
+    }
+    let valid_labels = name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            // Underscores are not allowed by the RFCs, but container runtimes hand them out.
+            && label
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+    });
+    // A numeric last label is a malformed IPv4 address, such as `256.0.0.1`, not a hostname.
+    let numeric_tld = name
+        .rsplit('.')
+        .next()
+        .is_some_and(|label| label.bytes().all(|byte| byte.is_ascii_digit()));
+    valid_labels && !numeric_tld
+}
+
+/// Joins a host and port, putting IPv6 addresses in brackets.
+pub(crate) fn host_port(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
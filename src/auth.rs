@@ -0,0 +1,156 @@
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use crate::errors::DatabaseConnectionErrors;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use surrealdb::{
+    engine::any::Any,
+    opt::auth::{Database, Namespace, Record, Root},
+    Surreal,
+};
+
+/// How a remote connection authenticates after connecting and before selecting the namespace
+/// and database. Namespace, database and record users are looked up in the namespace and
+/// database configured on the `DatabaseSettings`.
+///
+/// In config files each variant is a table named after it, such as
+/// `{ "database": { "username": "app", "password": "..." } }`, or the string `"none"`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Authentication {
+    /// Do not sign in, relying on guest access to the database.
+    #[default]
+    None,
+    /// Sign in as a root user.
+    Root { username: String, password: String },
+    /// Sign in as a user defined on the namespace.
+    Namespace { username: String, password: String },
+    /// Sign in as a user defined on the database.
+    Database { username: String, password: String },
+    /// Sign in through a record access method defined on the database,
+    /// passing the parameters its `SIGNIN` clause expects.
+    Record {
+        access: String,
+        params: BTreeMap<String, String>,
+    },
+    /// Authenticate with a previously issued JWT.
+    Token(String),
+}
+
+impl Authentication {
+    /// Root credentials, which is the most common way to connect to a server.
+    pub fn root(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Authentication::Root {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl Debug for Authentication {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Never print secrets, since settings are commonly logged at startup.
+        match self {
+            Authentication::None => write!(f, "None"),
+            Authentication::Root { username, .. } => f
+                .debug_struct("Root")
+                .field("username", username)
+                .field("password", &"REDACTED")
+                .finish(),
+            Authentication::Namespace { username, .. } => f
+                .debug_struct("Namespace")
+                .field("username", username)
+                .field("password", &"REDACTED")
+                .finish(),
+            Authentication::Database { username, .. } => f
+                .debug_struct("Database")
+                .field("username", username)
+                .field("password", &"REDACTED")
+                .finish(),
+            Authentication::Record { access, params } => f
+                .debug_struct("Record")
+                .field("access", access)
+                .field("params", &params.keys().collect::<Vec<_>>())
+                .finish(),
+            Authentication::Token(_) => write!(f, "Token(REDACTED)"),
+        }
+    }
+}
+
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+impl Authentication {
+    /// Checks that the credentials are set, returning the error for the first one that is not.
+    pub(crate) fn invalid_setting(&self) -> Option<DatabaseConnectionErrors> {
+        match self {
+            Authentication::None => None,
+            Authentication::Root { username, password }
+            | Authentication::Namespace { username, password }
+            | Authentication::Database { username, password } => {
+                if username.is_empty() {
+                    Some(DatabaseConnectionErrors::InvalidUsername)
+                } else if password.is_empty() {
+                    Some(DatabaseConnectionErrors::InvalidPassword)
+                } else {
+                    None
+                }
+            }
+            Authentication::Record { access, .. } if access.is_empty() => {
+                Some(DatabaseConnectionErrors::InvalidAccessMethod)
+            }
+            Authentication::Record { .. } => None,
+            Authentication::Token(token) if token.is_empty() => {
+                Some(DatabaseConnectionErrors::InvalidToken)
+            }
+            Authentication::Token(_) => None,
+        }
+    }
+
+    /// Signs the connection in, using the namespace and database the connection is for.
+    pub(crate) async fn apply(
+        &self,
+        conn: &Surreal<Any>,
+        namespace: &str,
+        database: &str,
+    ) -> Result<(), DatabaseConnectionErrors> {
+        let result = match self {
+            Authentication::None => return Ok(()),
+            Authentication::Root { username, password } => conn
+                .signin(Root {
+                    username: username.as_str(),
+                    password: password.as_str(),
+                })
+                .await
+                .map(|_| ()),
+            Authentication::Namespace { username, password } => conn
+                .signin(Namespace {
+                    namespace,
+                    username: username.as_str(),
+                    password: password.as_str(),
+                })
+                .await
+                .map(|_| ()),
+            Authentication::Database { username, password } => conn
+                .signin(Database {
+                    namespace,
+                    database,
+                    username: username.as_str(),
+                    password: password.as_str(),
+                })
+                .await
+                .map(|_| ()),
+            Authentication::Record { access, params } => conn
+                .signin(Record {
+                    namespace,
+                    database,
+                    access: access.as_str(),
+                    params,
+                })
+                .await
+                .map(|_| ()),
+            Authentication::Token(token) => conn.authenticate(token.as_str()).await,
+        };
+        result.map_err(|e| DatabaseConnectionErrors::AuthenticationFailed(Box::new(e)))
+    }
+}
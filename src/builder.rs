@@ -0,0 +1,276 @@
+use crate::auth::Authentication;
+use crate::cluster::ClusterSettings;
+use crate::errors::DatabaseConnectionErrors;
+use crate::tls::TlsSettings;
+use crate::{DatabaseSettings, DatabaseType};
+use std::marker::PhantomData;
+
+/// Marks a required builder field that has not been given yet.
+#[derive(Debug)]
+pub struct Missing;
+
+/// Marks a required builder field that has been given.
+#[derive(Debug)]
+pub struct Given;
+
+/// Builds `DatabaseSettings` for one database type, taking only the options that type uses.
+///
+/// The database type, namespace and database are required, and `build` is only available once
+/// all three are given, so leaving one out is a compile error. `build` then checks the values the
+/// same way `connect` does, so a misconfiguration is reported at startup instead of on the first
+/// checkout from the pool:
+///
+/// ```ignore
+/// let settings = DatabaseSettings::builder()
+///     .websocket("localhost", 8000)
+///     .namespace("app")
+///     .database("main")
+///     .root_auth("root", "secret")
+///     .build()?;
+/// ```
+#[derive(Debug)]
+pub struct DatabaseSettingsBuilder<Engine = Missing, Namespace = Missing, Database = Missing> {
+    db_type: Option<DatabaseType>,
+    auth: Authentication,
+    port: u16,
+    host: String,
+    path: String,
+    namespace: String,
+    database_name: String,
+    tls: Option<TlsSettings>,
+    cluster: Option<ClusterSettings>,
+    state: PhantomData<(Engine, Namespace, Database)>,
+}
+
+impl DatabaseSettings {
+    /// Starts building settings. See `DatabaseSettingsBuilder`.
+    pub fn builder() -> DatabaseSettingsBuilder {
+        DatabaseSettingsBuilder {
+            db_type: None,
+            auth: Authentication::None,
+            port: 0,
+            host: "".to_string(),
+            path: "".to_string(),
+            namespace: "".to_string(),
+            database_name: "".to_string(),
+            tls: None,
+            cluster: None,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<Engine, Namespace, Database> DatabaseSettingsBuilder<Engine, Namespace, Database> {
+    fn into_state<E, N, D>(self) -> DatabaseSettingsBuilder<E, N, D> {
+        DatabaseSettingsBuilder {
+            db_type: self.db_type,
+            auth: self.auth,
+            port: self.port,
+            host: self.host,
+            path: self.path,
+            namespace: self.namespace,
+            database_name: self.database_name,
+            tls: self.tls,
+            cluster: self.cluster,
+            state: PhantomData,
+        }
+    }
+
+    /// Sets how remote connections sign in.
+    pub fn auth(mut self, auth: Authentication) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Signs in as a root user.
+    pub fn root_auth(self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth(Authentication::root(username, password))
+    }
+
+    /// Sets the TLS options for `wss://` and `https://` connections.
+    pub fn tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Sets the cluster options for TiKV and FoundationDB.
+    pub fn cluster(mut self, cluster: ClusterSettings) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+}
+
+impl<Namespace, Database> DatabaseSettingsBuilder<Missing, Namespace, Database> {
+    fn engine(
+        mut self,
+        db_type: DatabaseType,
+        host: String,
+        port: u16,
+        path: String,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.db_type = Some(db_type);
+        self.host = host;
+        self.port = port;
+        self.path = path;
+        self.into_state()
+    }
+
+    /// Uses a datastore in memory, shared by every connection in the pool.
+    #[cfg(feature = "kv-memory")]
+    pub fn memory(self) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::Memory, "".to_string(), 0, "".to_string())
+    }
+
+    /// Uses a RocksDB datastore in the given folder.
+    #[cfg(feature = "kv-rocksdb")]
+    pub fn rocksdb(
+        self,
+        path: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::File, "".to_string(), 0, path.into())
+    }
+
+    /// Uses a SurrealKV datastore in the given folder.
+    #[cfg(feature = "kv-surreal")]
+    pub fn surrealkv(
+        self,
+        path: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::KeyValue, "".to_string(), 0, path.into())
+    }
+
+    /// Uses a SurrealKV datastore in the given folder that keeps old versions of records.
+    #[cfg(feature = "kv-surreal")]
+    pub fn surrealkv_versioned(
+        self,
+        path: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(
+            DatabaseType::VersionedKeyValue,
+            "".to_string(),
+            0,
+            path.into(),
+        )
+    }
+
+    /// Connects to a server over `ws://`.
+    #[cfg(feature = "kv-websocket")]
+    pub fn websocket(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::WebSocket, host.into(), port, "".to_string())
+    }
+
+    /// Connects to a server over `wss://`.
+    #[cfg(feature = "kv-websocket")]
+    pub fn websocket_secure(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(
+            DatabaseType::WebSocketSecure,
+            host.into(),
+            port,
+            "".to_string(),
+        )
+    }
+
+    /// Connects to a server over `http://`.
+    #[cfg(feature = "http")]
+    pub fn http(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::Http, host.into(), port, "".to_string())
+    }
+
+    /// Connects to a server over `https://`.
+    #[cfg(feature = "http")]
+    pub fn https(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::Https, host.into(), port, "".to_string())
+    }
+
+    /// Uses a TiKV cluster through the placement driver at the given host and port.
+    /// More placement drivers can be given with `cluster`.
+    #[cfg(feature = "kv-tikv")]
+    pub fn tikv(
+        self,
+        host: impl Into<String>,
+        port: u16,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(DatabaseType::TiKV, host.into(), port, "".to_string())
+    }
+
+    /// Uses a FoundationDB cluster described by the given cluster file, or by the default
+    /// cluster file when the path is empty.
+    #[cfg(feature = "kv-fdb")]
+    pub fn fdb(
+        self,
+        cluster_file: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Given, Namespace, Database> {
+        self.engine(
+            DatabaseType::FoundationDB,
+            "".to_string(),
+            0,
+            cluster_file.into(),
+        )
+    }
+}
+
+impl<Engine, Database> DatabaseSettingsBuilder<Engine, Missing, Database> {
+    /// Sets the namespace that connections use.
+    pub fn namespace(
+        mut self,
+        namespace: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Engine, Given, Database> {
+        self.namespace = namespace.into();
+        self.into_state()
+    }
+}
+
+impl<Engine, Namespace> DatabaseSettingsBuilder<Engine, Namespace, Missing> {
+    /// Sets the database that connections use.
+    pub fn database(
+        mut self,
+        database_name: impl Into<String>,
+    ) -> DatabaseSettingsBuilder<Engine, Namespace, Given> {
+        self.database_name = database_name.into();
+        self.into_state()
+    }
+}
+
+impl DatabaseSettingsBuilder<Given, Given, Given> {
+    /// Checks the settings and returns them. TLS files are loaded to check that they can be used.
+    pub fn build(self) -> Result<DatabaseSettings, DatabaseConnectionErrors> {
+        let settings = DatabaseSettings {
+            db_type: self
+                .db_type
+                .expect("the database type is set before the builder is marked as given"),
+            auth: self.auth,
+            port: self.port,
+            host: self.host,
+            path: self.path,
+            namespace: self.namespace,
+            database_name: self.database_name,
+            tls: self.tls,
+            cluster: self.cluster,
+        };
+        if let Some(error) = settings.invalid_setting() {
+            return Err(error);
+        }
+        #[cfg(any(feature = "kv-websocket", feature = "http"))]
+        if let Some(tls) = &settings.tls {
+            tls.client_config()
+                .map_err(DatabaseConnectionErrors::InvalidTlsConfiguration)?;
+        }
+        Ok(settings)
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(feature = "kv-tikv")]
+use crate::embedded::EmbeddedDatastore;
+#[cfg(feature = "kv-tikv")]
+use crate::errors::DatabaseConnectionErrors;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+#[cfg(feature = "kv-tikv")]
+use tokio::time;
+
+/// Options for the distributed TiKV (`tikv://`) and FoundationDB (`fdb://`) database types.
+/// The FoundationDB cluster file is given by the settings path, and the default cluster file is
+/// used when the path is empty.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterSettings {
+    /// TiKV placement driver endpoints as `host:port`, tried in order until the datastore
+    /// opens through one. When empty, the host and port of the settings are used.
+    pub pd_endpoints: Vec<String>,
+    /// How long a transaction may run before it is cancelled.
+    pub transaction_timeout: Option<Duration>,
+}
+
+/// How long an endpoint has to open the datastore before the next one is tried.
+#[cfg(feature = "kv-tikv")]
+const ENDPOINT_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Opens the TiKV datastore through each placement driver endpoint in turn, as the client only
+/// takes one, and returns the first that opens.
+#[cfg(feature = "kv-tikv")]
+pub(crate) async fn open_tikv(
+    endpoints: Vec<String>,
+    transaction_timeout: Option<Duration>,
+) -> Result<EmbeddedDatastore, DatabaseConnectionErrors> {
+    for endpoint in &endpoints {
+        let open = EmbeddedDatastore::open(format!("tikv://{}", endpoint), transaction_timeout);
+        if let Ok(Ok(datastore)) = time::timeout(ENDPOINT_OPEN_TIMEOUT, open).await {
+            return Ok(datastore);
+        }
+    }
+    Err(DatabaseConnectionErrors::UnreachableEndpoints(endpoints))
+}
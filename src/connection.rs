@@ -0,0 +1,205 @@
+use crate::session::SessionChanges;
+use serde::Serialize;
+use std::borrow::Borrow;
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use std::future::IntoFuture;
+use std::io::ErrorKind;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use std::time::Duration;
+use surrealdb::engine::any::Any;
+use surrealdb::err::Error as DbError;
+use surrealdb::error::Api;
+use surrealdb::method::{Authenticate, Invalidate, Set, Signin, Signup, Unset};
+use surrealdb::opt::auth::{self, Credentials, Jwt};
+use surrealdb::Surreal;
+use tokio::task::AbortHandle;
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use tokio::time;
+
+/// A connection handed out by the pool. It dereferences to the `Surreal` client, so queries are
+/// made on it directly, and tracks whether the connection is still alive so that the pool can
+/// discard it rather than hand a dead connection to the next caller.
+///
+/// Remote connections are pinged in the background, and are marked as broken when a ping fails or
+/// is not answered in time. Errors seen while the connection is checked out can be reported
+/// with `observe`.
+///
+/// Parameters set and sign ins made with the methods of the connection are recorded, so that the
+/// pool can undo them before handing the connection to the next caller. See `SessionReset`.
+#[derive(Debug)]
+pub struct SurrealConnection {
+    client: Surreal<Any>,
+    broken: Arc<AtomicBool>,
+    watchdog: Option<AbortHandle>,
+    session: Mutex<SessionChanges>,
+}
+
+impl SurrealConnection {
+    /// Wraps a connection that is not watched, such as a handle to an embedded datastore.
+    pub(crate) fn new(client: Surreal<Any>) -> Self {
+        SurrealConnection {
+            client,
+            broken: Arc::new(AtomicBool::new(false)),
+            watchdog: None,
+            session: Mutex::new(SessionChanges::default()),
+        }
+    }
+
+    /// Wraps a remote connection, pinging it every interval until a ping fails or times out.
+    #[cfg(any(feature = "kv-websocket", feature = "http"))]
+    pub(crate) fn watched(client: Surreal<Any>, interval: Duration) -> Self {
+        let mut connection = SurrealConnection::new(client);
+        let watchdog = tokio::spawn(watch(
+            connection.client.clone(),
+            connection.broken.clone(),
+            interval,
+        ));
+        connection.watchdog = Some(watchdog.abort_handle());
+        connection
+    }
+
+    /// Whether the connection has been found to be broken.
+    pub fn is_broken(&self) -> bool {
+        self.broken.load(Ordering::Relaxed)
+    }
+
+    /// Marks the connection as broken, so the pool closes it instead of reusing it.
+    pub fn mark_broken(&self) {
+        self.broken.store(true, Ordering::Relaxed);
+    }
+
+    /// Marks the connection as broken when the result is an error from the connection itself,
+    /// such as a closed socket or a failed request, and passes the result through unchanged.
+    /// Errors from the query, such as a failed statement or a permission error, leave the
+    /// connection usable.
+    pub fn observe<T, E: Borrow<surrealdb::Error>>(&self, result: Result<T, E>) -> Result<T, E> {
+        if let Err(error) = &result {
+            if is_connection_error(error.borrow()) {
+                self.mark_broken();
+            }
+        }
+        result
+    }
+
+    /// Assigns a parameter on the session, like `Surreal::set`, and records it so that it is
+    /// unset before the connection is handed to the next caller.
+    pub fn set(&self, key: impl Into<String>, value: impl Serialize + 'static) -> Set<'_, Any> {
+        let key = key.into();
+        self.changes().parameters.insert(key.clone());
+        self.client.set(key, value)
+    }
+
+    /// Removes a parameter from the session, like `Surreal::unset`.
+    pub fn unset(&self, key: impl Into<String>) -> Unset<'_, Any> {
+        let key = key.into();
+        self.changes().parameters.remove(&key);
+        self.client.unset(key)
+    }
+
+    /// Signs in, like `Surreal::signin`, and records that the session changed user.
+    pub fn signin<R>(&self, credentials: impl Credentials<auth::Signin, R>) -> Signin<'_, Any, R> {
+        self.changes().authentication = true;
+        self.client.signin(credentials)
+    }
+
+    /// Signs up, like `Surreal::signup`, and records that the session changed user.
+    pub fn signup<R>(&self, credentials: impl Credentials<auth::Signup, R>) -> Signup<'_, Any, R> {
+        self.changes().authentication = true;
+        self.client.signup(credentials)
+    }
+
+    /// Authenticates with a token, like `Surreal::authenticate`, and records that the session
+    /// changed user.
+    pub fn authenticate(&self, token: impl Into<Jwt>) -> Authenticate<'_, Any> {
+        self.changes().authentication = true;
+        self.client.authenticate(token)
+    }
+
+    /// Signs out, like `Surreal::invalidate`, and records that the session changed user.
+    pub fn invalidate(&self) -> Invalidate<'_, Any> {
+        self.changes().authentication = true;
+        self.client.invalidate()
+    }
+
+    /// Returns the changes made to the session since the last call, and forgets them.
+    pub(crate) fn take_session_changes(&self) -> SessionChanges {
+        mem::take(&mut *self.changes())
+    }
+
+    fn changes(&self) -> std::sync::MutexGuard<'_, SessionChanges> {
+        // The changes are left consistent by every operation, so a panic elsewhere cannot
+        // poison them.
+        self.session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Deref for SurrealConnection {
+    type Target = Surreal<Any>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for SurrealConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+impl Drop for SurrealConnection {
+    fn drop(&mut self) {
+        // The watchdog holds a handle to the client, which would keep the connection open.
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+    }
+}
+
+/// Pings the connection every interval, and marks it as broken when a ping fails or is not
+/// answered within the interval. The remote engines reconnect on their own and hold requests
+/// until they do, so a ping that is not answered is the only sign of a dead server.
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+async fn watch(client: Surreal<Any>, broken: Arc<AtomicBool>, interval: Duration) {
+    let mut ticks = time::interval_at(time::Instant::now() + interval, interval);
+    loop {
+        ticks.tick().await;
+        if broken.load(Ordering::Relaxed) {
+            return;
+        }
+        match time::timeout(interval, client.health().into_future()).await {
+            Ok(Ok(())) => {}
+            _ => {
+                broken.store(true, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+}
+
+/// Whether an error comes from the connection to the database rather than from a query.
+pub(crate) fn is_connection_error(error: &surrealdb::Error) -> bool {
+    match error {
+        surrealdb::Error::Api(error) => matches!(
+            error,
+            Api::Http(_) | Api::Ws(_) | Api::ConnectionUninitialised | Api::InternalError(_)
+        ),
+        surrealdb::Error::Db(DbError::Io(error)) => matches!(
+            error.kind(),
+            ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::NotConnected
+                | ErrorKind::TimedOut
+                | ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,282 @@
+use crate::auth::Authentication;
+use crate::errors::DatabaseConnectionErrors;
+use crate::{DatabaseSettings, DatabaseType};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use url::form_urlencoded;
+use url::{ParseError, Url};
+
+/// Characters escaped in a path, which keeps `?` and `#` from ending the path early.
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters escaped in a single path segment, such as the namespace and database name.
+const SEGMENT: &AsciiSet = &PATH.add(b'/');
+
+/// Characters escaped in the username of the user info.
+const USERINFO: &AsciiSet = &SEGMENT
+    .add(b':')
+    .add(b';')
+    .add(b'=')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'|');
+
+/// Replaces passwords, tokens and record access parameters in URLs built by `to_url`.
+const REDACTED: &str = "REDACTED";
+
+impl DatabaseSettings {
+    /// Parses settings from a connection URL.
+    ///
+    /// Networked database types take the namespace and database from the path and the credentials
+    /// from the user info, as in `ws://user:pass@localhost:8000/app/main`. Other types take the
+    /// path of the datastore, as in `rocksdb:///var/data?ns=app&db=main`. The `ns` and `db` query
+    /// parameters can be used with either form.
+    ///
+    /// User info signs in as a root user, unless the `auth` query parameter selects `namespace`
+    /// or `database` users. `auth=record&access=account` signs in through a record access method,
+    /// passing every other query parameter to it, and `auth=token&token=...` uses a JWT.
+    pub fn from_url(url: &str) -> Result<Self, DatabaseConnectionErrors> {
+        url.parse()
+    }
+
+    /// Builds the connection URL for these settings, which `from_url` parses back into the same
+    /// settings. Passwords, tokens and record access parameters are replaced with `REDACTED`,
+    /// so the URL is safe to log. TLS settings are not included.
+    pub fn to_url(&self) -> String {
+        let mut url = format!("{}://", self.db_type.scheme());
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        if self.db_type.is_networked() {
+            let (username, auth) = match &self.auth {
+                Authentication::None => (None, None),
+                Authentication::Root { username, .. } => (Some(username), None),
+                Authentication::Namespace { username, .. } => (Some(username), Some("namespace")),
+                Authentication::Database { username, .. } => (Some(username), Some("database")),
+                Authentication::Record { .. } => (None, Some("record")),
+                Authentication::Token(_) => (None, Some("token")),
+            };
+            if let Some(username) = username {
+                url.push_str(&utf8_percent_encode(username, USERINFO).to_string());
+                url.push(':');
+                url.push_str(REDACTED);
+                url.push('@');
+            }
+            url.push_str(&self.address());
+            for segment in [&self.namespace, &self.database_name] {
+                url.push('/');
+                url.push_str(&utf8_percent_encode(segment, SEGMENT).to_string());
+            }
+            if let Some(auth) = auth {
+                query.append_pair("auth", auth);
+            }
+            match &self.auth {
+                Authentication::Record { access, params } => {
+                    query.append_pair("access", access);
+                    for key in params.keys() {
+                        query.append_pair(key, REDACTED);
+                    }
+                }
+                Authentication::Token(_) => {
+                    query.append_pair("token", REDACTED);
+                }
+                _ => {}
+            }
+        } else {
+            url.push_str(&utf8_percent_encode(&self.path, PATH).to_string());
+            query.append_pair("ns", &self.namespace);
+            query.append_pair("db", &self.database_name);
+        }
+        let query = query.finish();
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+        url
+    }
+}
+
+impl FromStr for DatabaseSettings {
+    type Err = DatabaseConnectionErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let settings = DatabaseSettings::parse_url(s)?;
+        if settings.namespace.is_empty() {
+            return Err(DatabaseConnectionErrors::InvalidNamespace);
+        }
+        if settings.database_name.is_empty() {
+            return Err(DatabaseConnectionErrors::InvalidDatabaseName);
+        }
+        Ok(settings)
+    }
+}
+
+impl DatabaseSettings {
+    /// Parses a connection URL, leaving the namespace and database empty when it does not set them.
+    pub(crate) fn parse_url(s: &str) -> Result<Self, DatabaseConnectionErrors> {
+        let url = Url::parse(s).map_err(|error| match error {
+            ParseError::InvalidPort => DatabaseConnectionErrors::InvalidPort(rejected_port(s)),
+            error => DatabaseConnectionErrors::InvalidUrl(error),
+        })?;
+        let db_type = DatabaseType::from_scheme(url.scheme())
+            .ok_or_else(|| DatabaseConnectionErrors::UnsupportedScheme(url.scheme().to_string()))?;
+        let mut query = BTreeMap::new();
+        for (key, value) in url.query_pairs() {
+            if query.insert(key.to_string(), value.to_string()).is_some() {
+                return Err(DatabaseConnectionErrors::InvalidUrlParameter(format!(
+                    "`{}` is given more than once",
+                    key
+                )));
+            }
+        }
+
+        let mut settings = DatabaseSettings {
+            db_type,
+            auth: Authentication::None,
+            port: 0,
+            host: "".to_string(),
+            path: "".to_string(),
+            namespace: "".to_string(),
+            database_name: "".to_string(),
+            tls: None,
+            cluster: None,
+        };
+        if settings.db_type.is_networked() {
+            // IPv6 addresses are kept without brackets, as they are when set in code.
+            settings.host = url
+                .host_str()
+                .filter(|host| !host.is_empty())
+                .ok_or_else(|| DatabaseConnectionErrors::InvalidHost("".to_string()))?
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string();
+            settings.port = url
+                .port()
+                .or_else(|| settings.db_type.default_port())
+                .ok_or_else(|| DatabaseConnectionErrors::InvalidPort("".to_string()))?;
+            let mut segments = url
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .filter(|segment| !segment.is_empty())
+                .map(decode);
+            settings.namespace = segments.next().unwrap_or_default();
+            settings.database_name = segments.next().unwrap_or_default();
+            if segments.next().is_some() {
+                return Err(DatabaseConnectionErrors::InvalidUrlParameter(
+                    "the path only takes a namespace and a database".to_string(),
+                ));
+            }
+
+            // The authentication comes from the user info and the `auth` query parameter.
+            let username = decode(url.username());
+            let password = url.password().map(decode).unwrap_or_default();
+            let has_credentials = !username.is_empty() || url.password().is_some();
+            let method = query.remove("auth");
+            let method = match method.as_deref() {
+                Some(method) => method,
+                None if has_credentials => "root",
+                None => "none",
+            };
+            if has_credentials && matches!(method, "none" | "record" | "token") {
+                return Err(DatabaseConnectionErrors::InvalidUrlParameter(format!(
+                    "`auth={}` does not take a username or password",
+                    method
+                )));
+            }
+            if matches!(method, "root" | "namespace" | "database") {
+                if username.is_empty() {
+                    return Err(DatabaseConnectionErrors::InvalidUsername);
+                }
+                if password.is_empty() {
+                    return Err(DatabaseConnectionErrors::InvalidPassword);
+                }
+            }
+            settings.auth = match method {
+                "none" => Authentication::None,
+                "root" => Authentication::Root { username, password },
+                "namespace" => Authentication::Namespace { username, password },
+                "database" => Authentication::Database { username, password },
+                "record" => {
+                    let access = query
+                        .remove("access")
+                        .filter(|access| !access.is_empty())
+                        .ok_or(DatabaseConnectionErrors::InvalidAccessMethod)?;
+                    // Every parameter other than the namespace and database goes to the access method.
+                    let params;
+                    (query, params) = std::mem::take(&mut query)
+                        .into_iter()
+                        .partition(|(key, _)| matches!(key.as_str(), "ns" | "db"));
+                    Authentication::Record { access, params }
+                }
+                "token" => {
+                    let token = query
+                        .remove("token")
+                        .filter(|token| !token.is_empty())
+                        .ok_or(DatabaseConnectionErrors::InvalidToken)?;
+                    Authentication::Token(token)
+                }
+                method => {
+                    return Err(DatabaseConnectionErrors::InvalidUrlParameter(format!(
+                        "`auth={}` is not one of none, root, namespace, database, record or token",
+                        method
+                    )))
+                }
+            };
+        } else {
+            if !url.username().is_empty() || url.password().is_some() || url.port().is_some() {
+                return Err(DatabaseConnectionErrors::InvalidUrlParameter(format!(
+                    "`{}` URLs do not take credentials or a port",
+                    url.scheme()
+                )));
+            }
+            settings.path = decode(&format!("{}{}", url.host_str().unwrap_or(""), url.path()));
+        }
+
+        if let Some(namespace) = query.remove("ns") {
+            settings.namespace = namespace;
+        }
+        if let Some(database_name) = query.remove("db") {
+            settings.database_name = database_name;
+        }
+        if let Some(key) = query.keys().next() {
+            return Err(DatabaseConnectionErrors::InvalidUrlParameter(format!(
+                "`{}` is not a known parameter",
+                key
+            )));
+        }
+        Ok(settings)
+    }
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+/// The port of a URL that could not be parsed because of it, as it was written.
+fn rejected_port(s: &str) -> String {
+    let rest = s.split_once("://").map_or(s, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host_port)| host_port);
+    // The port follows the closing bracket of an IPv6 address.
+    let port = host_port
+        .rsplit_once(']')
+        .map_or(host_port, |(_, port)| port);
+    port.rsplit_once(':')
+        .map_or("", |(_, port)| port)
+        .to_string()
+}
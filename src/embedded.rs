@@ -0,0 +1,371 @@
+use crate::errors::DatabaseConnectionErrors;
+use futures::{SinkExt, StreamExt};
+use revision::{revisioned, Revisioned};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::panic;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Duration;
+use surrealdb::dbs::{Notification, Session};
+use surrealdb::engine::any;
+use surrealdb::engine::any::Any;
+use surrealdb::engine::tasks;
+use surrealdb::err::Error as DbError;
+use surrealdb::kvs::Datastore;
+use surrealdb::options::EngineOptions;
+use surrealdb::rpc::format::Format;
+use surrealdb::rpc::{Data, RpcContext, RpcError, RpcProtocolV1, RpcProtocolV2};
+use surrealdb::sql::Value;
+use surrealdb::{Surreal, Uuid};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+/// An in-process datastore that is opened once and shared by every pooled connection.
+///
+/// The embedded SurrealDB client keeps one session for its datastore, so the namespace,
+/// parameters and sign in changed by one of its handles would change for every other handle.
+/// Instead the datastore is served over a WebSocket on a loopback port, and each connection is a
+/// client of its own with a session of its own, as it would be with a remote server. The address
+/// holds a random token, so that other processes on the host cannot reach the datastore.
+///
+/// SurrealDB spawns the client router onto whichever runtime connects, so the datastore, its
+/// server and its clients are given their own runtime. This keeps them alive when the runtime
+/// that opened them shuts down, which happens with per-test runtimes or per-worker runtimes in
+/// web servers.
+pub(crate) struct EmbeddedDatastore {
+    runtime: EmbeddedRuntime,
+    address: String,
+}
+
+/// The runtime of an embedded datastore. It is shut down without blocking when dropped, as
+/// managers are dropped from within an async context, and so is a connect that is cancelled while
+/// the datastore is being opened.
+struct EmbeddedRuntime(Option<Runtime>);
+
+impl EmbeddedRuntime {
+    fn handle(&self) -> &Handle {
+        // The runtime is only taken when it is dropped.
+        self.0.as_ref().unwrap().handle()
+    }
+}
+
+impl Drop for EmbeddedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl EmbeddedDatastore {
+    /// Opens the datastore at the given path, such as `memory` or `rocksdb://path/to/folder`,
+    /// and starts serving it.
+    pub(crate) async fn open(
+        path: String,
+        transaction_timeout: Option<Duration>,
+    ) -> Result<Self, DatabaseConnectionErrors> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("bb8-surrealdb-embedded")
+            .enable_all()
+            .build()
+            .map_err(|e| {
+                DatabaseConnectionErrors::ConnectFailed(Box::new(DbError::Io(e).into()))
+            })?;
+        let runtime = EmbeddedRuntime(Some(runtime));
+        let started = runtime
+            .handle()
+            .spawn(serve(path, transaction_timeout))
+            .await;
+        let address = match started {
+            Ok(result) => result?,
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        };
+        Ok(EmbeddedDatastore { runtime, address })
+    }
+
+    /// Opens a new connection to the datastore, with a session of its own.
+    pub(crate) async fn connect(&self) -> Result<Surreal<Any>, DatabaseConnectionErrors> {
+        let address = self.address.clone();
+        let connected = self
+            .runtime
+            .handle()
+            .spawn(async move { any::connect(address).await })
+            .await;
+        match connected {
+            Ok(result) => result.map_err(|e| DatabaseConnectionErrors::ConnectFailed(Box::new(e))),
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        }
+    }
+}
+
+/// The state shared by every connection to an embedded datastore.
+struct Server {
+    kvs: Arc<Datastore>,
+    /// The request path of the WebSocket handshake, which holds the random token.
+    path: String,
+    /// The connection that started each live query, to send its notifications to.
+    live_queries: Mutex<HashMap<Uuid, mpsc::UnboundedSender<Notification>>>,
+}
+
+impl Server {
+    fn live_queries(&self) -> MutexGuard<'_, HashMap<Uuid, mpsc::UnboundedSender<Notification>>> {
+        // Every operation leaves the map consistent, so a panic elsewhere cannot poison it.
+        self.live_queries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Opens the datastore as the embedded engine does, starts serving it on a free loopback port,
+/// and returns the address that clients connect to.
+async fn serve(
+    path: String,
+    transaction_timeout: Option<Duration>,
+) -> Result<String, DatabaseConnectionErrors> {
+    let failed = |error: DbError| DatabaseConnectionErrors::ConnectFailed(Box::new(error.into()));
+    let kvs = Datastore::new(&path).await.map_err(failed)?;
+    kvs.check_version().await.map_err(failed)?;
+    kvs.bootstrap().await.map_err(failed)?;
+    let kvs = Arc::new(
+        kvs.with_notifications()
+            .with_transaction_timeout(transaction_timeout),
+    );
+    // Node membership and changefeed upkeep, which stops with the runtime.
+    tasks::init(
+        kvs.clone(),
+        CancellationToken::new(),
+        &EngineOptions::default(),
+    );
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .map_err(|e| failed(DbError::Io(e)))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| failed(DbError::Io(e)))?
+        .port();
+    let token = Uuid::new_v4().simple().to_string();
+    let server = Arc::new(Server {
+        kvs,
+        path: format!("/{}/rpc", token),
+        live_queries: Mutex::new(HashMap::new()),
+    });
+    tokio::spawn(route_notifications(server.clone()));
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_connection(stream, server.clone()));
+        }
+    });
+    // The client adds the `rpc` path to the address.
+    Ok(format!("ws://127.0.0.1:{}/{}/", port, token))
+}
+
+/// Sends the notifications of each live query to the connection that started it.
+async fn route_notifications(server: Arc<Server>) {
+    let Some(notifications) = server.kvs.notifications() else {
+        return;
+    };
+    while let Ok(notification) = notifications.recv().await {
+        if let Some(connection) = server.live_queries().get(&notification.id.0) {
+            let _ = connection.send(notification);
+        }
+    }
+}
+
+/// Serves RPC messages on one WebSocket connection, which keeps its session between calls.
+async fn serve_connection(stream: TcpStream, server: Arc<Server>) {
+    let handshake = Handshake { path: &server.path };
+    let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, handshake).await else {
+        return;
+    };
+    let (sender, mut notifications) = mpsc::unbounded_channel();
+    let context = Connection {
+        server: server.clone(),
+        lock: Arc::new(Semaphore::new(1)),
+        session: RwLock::new(Arc::new(Session::default().with_rt(true))),
+        notifications: sender,
+    };
+    loop {
+        let response = tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(Message::Binary(body))) => context.call(body).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(notification) = notifications.recv() => encode(RpcResponse {
+                id: None,
+                result: Ok(Data::Live(notification)),
+            }),
+        };
+        if socket.send(Message::Binary(response)).await.is_err() {
+            break;
+        }
+    }
+    context.cleanup_lqs().await;
+}
+
+/// Accepts WebSocket handshakes on the path that holds the token.
+struct Handshake<'a> {
+    path: &'a str,
+}
+
+impl Callback for Handshake<'_> {
+    fn on_request(
+        self,
+        request: &Request,
+        mut response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        if request.uri().path() != self.path {
+            let mut refused = ErrorResponse::new(None);
+            *refused.status_mut() = StatusCode::NOT_FOUND;
+            return Err(refused);
+        }
+        // The client only speaks the `revision` format.
+        response
+            .headers_mut()
+            .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("revision"));
+        Ok(response)
+    }
+}
+
+/// The RPC state of one WebSocket connection.
+struct Connection {
+    server: Arc<Server>,
+    lock: Arc<Semaphore>,
+    session: RwLock<Arc<Session>>,
+    /// Where the notifications of the live queries started on this connection are sent.
+    notifications: mpsc::UnboundedSender<Notification>,
+}
+
+impl Connection {
+    /// Runs a single RPC request and encodes its response.
+    async fn call(&self, body: Vec<u8>) -> Vec<u8> {
+        let (id, result) = match Format::Revision.req(body) {
+            Ok(request) => {
+                let result =
+                    RpcContext::execute(self, request.version, request.method, request.params)
+                        .await;
+                (request.id, result)
+            }
+            Err(error) => (None, Err(error)),
+        };
+        encode(RpcResponse {
+            id,
+            result: result.map_err(Failure::from),
+        })
+    }
+}
+
+impl RpcContext for Connection {
+    fn kvs(&self) -> &Datastore {
+        &self.server.kvs
+    }
+
+    fn lock(&self) -> Arc<Semaphore> {
+        self.lock.clone()
+    }
+
+    fn session(&self) -> Arc<Session> {
+        self.session
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set_session(&self, session: Arc<Session>) {
+        *self.session.write().unwrap_or_else(PoisonError::into_inner) = session;
+    }
+
+    fn version_data(&self) -> Data {
+        Value::from(format!("surrealdb-{}", surrealdb::env::VERSION)).into()
+    }
+
+    const LQ_SUPPORT: bool = true;
+
+    async fn handle_live(&self, id: &Uuid) {
+        self.server
+            .live_queries()
+            .insert(*id, self.notifications.clone());
+    }
+
+    async fn handle_kill(&self, id: &Uuid) {
+        self.server.live_queries().remove(id);
+    }
+
+    /// Stops the live queries started on this connection once it closes.
+    async fn cleanup_lqs(&self) {
+        let ids: Vec<Uuid> = {
+            let mut live_queries = self.server.live_queries();
+            let ids = live_queries
+                .iter()
+                .filter(|(_, connection)| connection.same_channel(&self.notifications))
+                .map(|(id, _)| *id)
+                .collect();
+            live_queries.retain(|_, connection| !connection.same_channel(&self.notifications));
+            ids
+        };
+        if !ids.is_empty() {
+            let _ = self.server.kvs.delete_queries(ids).await;
+        }
+    }
+}
+
+impl RpcProtocolV1 for Connection {}
+
+impl RpcProtocolV2 for Connection {}
+
+/// The RPC response envelope expected by the SurrealDB client.
+#[revisioned(revision = 1)]
+struct RpcResponse {
+    id: Option<Value>,
+    result: Result<Data, Failure>,
+}
+
+#[revisioned(revision = 1)]
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+impl From<RpcError> for Failure {
+    fn from(error: RpcError) -> Self {
+        let code = match error {
+            RpcError::ParseError => -32700,
+            RpcError::InvalidRequest => -32600,
+            RpcError::MethodNotFound => -32601,
+            RpcError::InvalidParams => -32602,
+            _ => -32000,
+        };
+        Failure {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Encodes a response in the `revision` format.
+fn encode(response: RpcResponse) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    if let Err(error) = response.serialize_revisioned(&mut buffer) {
+        // Answer the request rather than leave the client waiting for a response.
+        buffer.clear();
+        let failure = RpcResponse {
+            id: response.id,
+            result: Err(Failure {
+                code: -32000,
+                message: error.to_string(),
+            }),
+        };
+        let _ = failure.serialize_revisioned(&mut buffer);
+    }
+    buffer
+}
@@ -0,0 +1,171 @@
+use crate::auth::Authentication;
+use crate::errors::DatabaseConnectionErrors;
+use crate::tls::TlsSettings;
+use crate::DatabaseSettings;
+use std::env;
+use std::env::VarError;
+
+type Result<T> = std::result::Result<T, DatabaseConnectionErrors>;
+
+/// Reads the environment variables that share a prefix, such as `SURREAL_URL` and `SURREAL_NS`.
+struct Variables<'a> {
+    prefix: &'a str,
+}
+
+impl Variables<'_> {
+    fn name(&self, suffix: &str) -> String {
+        format!("{}_{}", self.prefix, suffix)
+    }
+
+    /// The value of a variable, treating an empty value as unset.
+    fn get(&self, suffix: &str) -> Result<Option<String>> {
+        match env::var(self.name(suffix)) {
+            Ok(value) if value.is_empty() => Ok(None),
+            Ok(value) => Ok(Some(value)),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(_)) => {
+                Err(self.invalid(suffix, "the value is not valid unicode".to_string()))
+            }
+        }
+    }
+
+    /// The value of a variable that must be set.
+    fn require(&self, suffix: &str, value: Option<String>) -> Result<String> {
+        value.ok_or_else(|| self.missing(suffix))
+    }
+
+    fn missing(&self, suffix: &str) -> DatabaseConnectionErrors {
+        DatabaseConnectionErrors::MissingEnvironmentVariable(self.name(suffix))
+    }
+
+    fn invalid(&self, suffix: &str, reason: String) -> DatabaseConnectionErrors {
+        DatabaseConnectionErrors::InvalidEnvironmentVariable {
+            name: self.name(suffix),
+            reason,
+        }
+    }
+
+    fn read(&self) -> Result<DatabaseSettings> {
+        let url = self.require("URL", self.get("URL")?)?;
+        let mut settings = DatabaseSettings::parse_url(&url)
+            .map_err(|error| self.invalid("URL", error.to_string()))?;
+
+        if let Some(namespace) = self.get("NS")? {
+            settings.namespace = namespace;
+        }
+        if settings.namespace.is_empty() {
+            return Err(self.missing("NS"));
+        }
+        if let Some(database_name) = self.get("DB")? {
+            settings.database_name = database_name;
+        }
+        if settings.database_name.is_empty() {
+            return Err(self.missing("DB"));
+        }
+
+        let mut username = self.get("USER")?;
+        let mut password = self.get("PASS")?;
+        let mut access = self.get("ACCESS")?;
+        let token = self.get("TOKEN")?;
+        let method = match self.get("AUTH")? {
+            Some(method) => method,
+            None if token.is_some() => "token".to_string(),
+            None if access.is_some() => "record".to_string(),
+            None if username.is_some() || password.is_some() => "root".to_string(),
+            // Without any authentication variables, the authentication in the URL is used.
+            None => return self.read_tls(settings),
+        };
+        // Credentials and record parameters that are not given as variables come from the URL.
+        let mut params = Default::default();
+        match &settings.auth {
+            Authentication::Root {
+                username: url_username,
+                password: url_password,
+            }
+            | Authentication::Namespace {
+                username: url_username,
+                password: url_password,
+            }
+            | Authentication::Database {
+                username: url_username,
+                password: url_password,
+            } => {
+                username.get_or_insert_with(|| url_username.clone());
+                password.get_or_insert_with(|| url_password.clone());
+            }
+            Authentication::Record {
+                access: url_access,
+                params: url_params,
+            } => {
+                access.get_or_insert_with(|| url_access.clone());
+                params = url_params.clone();
+            }
+            _ => {}
+        }
+        settings.auth = match method.as_str() {
+            "none" => Authentication::None,
+            "root" => Authentication::Root {
+                username: self.require("USER", username)?,
+                password: self.require("PASS", password)?,
+            },
+            "namespace" => Authentication::Namespace {
+                username: self.require("USER", username)?,
+                password: self.require("PASS", password)?,
+            },
+            "database" => Authentication::Database {
+                username: self.require("USER", username)?,
+                password: self.require("PASS", password)?,
+            },
+            "record" => Authentication::Record {
+                access: self.require("ACCESS", access)?,
+                params,
+            },
+            "token" => Authentication::Token(self.require("TOKEN", token)?),
+            method => {
+                return Err(self.invalid(
+                    "AUTH",
+                    format!(
+                        "`{}` is not one of none, root, namespace, database, record or token",
+                        method
+                    ),
+                ))
+            }
+        };
+        self.read_tls(settings)
+    }
+
+    fn read_tls(&self, mut settings: DatabaseSettings) -> Result<DatabaseSettings> {
+        let ca_bundle = self.get("CA_BUNDLE")?;
+        let client_certificate = self.get("CLIENT_CERT")?;
+        let client_key = self.get("CLIENT_KEY")?;
+        if ca_bundle.is_some() || client_certificate.is_some() || client_key.is_some() {
+            settings.tls = Some(TlsSettings {
+                ca_bundle,
+                client_certificate,
+                client_key,
+                ..Default::default()
+            });
+        }
+        Ok(settings)
+    }
+}
+
+impl DatabaseSettings {
+    /// Reads settings from environment variables that start with the given prefix.
+    ///
+    /// `{prefix}_URL` is required and is parsed like `from_url`. The other variables override the
+    /// parts of the URL they cover, so that secrets can be kept out of it:
+    ///
+    /// * `{prefix}_NS` and `{prefix}_DB` select the namespace and database.
+    /// * `{prefix}_USER` and `{prefix}_PASS` sign in as a root user, unless `{prefix}_AUTH`
+    ///   is `namespace` or `database`.
+    /// * `{prefix}_ACCESS` signs in through a record access method, with the parameters given in
+    ///   the URL, and `{prefix}_TOKEN` authenticates with a JWT. `{prefix}_AUTH` can also be set
+    ///   to `record`, `token` or `none`.
+    /// * `{prefix}_CA_BUNDLE`, `{prefix}_CLIENT_CERT` and `{prefix}_CLIENT_KEY` set the TLS files.
+    ///
+    /// A required variable that is not set is reported with its name.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        Variables { prefix }.read()
+    }
+}
@@ -1,21 +1,143 @@
+use crate::connection::is_connection_error;
+use crate::DatabaseSettings;
+use bb8::RunError;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result};
+use std::io;
+use std::path::PathBuf;
+use surrealdb::RecordIdKey;
 
+/// The error returned by the connection manager. It holds the cause and, for errors from
+/// connecting or checking a connection, the settings the connection was made with.
 #[derive(Debug)]
 pub struct ConnectionError {
     pub error: DatabaseConnectionErrors,
+    /// The database the connection was for, when the error came from the connection manager.
+    pub context: Option<Box<ConnectionContext>>,
+}
+
+impl ConnectionError {
+    /// Adds the settings the connection was made with.
+    pub(crate) fn with_context(mut self, settings: &DatabaseSettings) -> Self {
+        self.context = Some(Box::new(ConnectionContext::from(settings)));
+        self
+    }
+
+    /// What kind of failure this is. See `ErrorKind`.
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// Whether the failure is transient, so that trying again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+}
+
+/// The kinds of failure, for deciding whether an error is worth retrying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The settings are missing or invalid. Retrying cannot succeed until they are fixed.
+    Config,
+    /// The credentials were rejected.
+    Auth,
+    /// The database could not be reached, or the connection to it was lost.
+    Network,
+    /// The database was reached but answered with an error.
+    Server,
+    /// The database did not answer in time.
+    Timeout,
+}
+
+impl ErrorKind {
+    /// Whether errors of this kind are transient. Only network failures and timeouts are, as
+    /// the others fail the same way until the settings, credentials or database are changed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorKind::Network | ErrorKind::Timeout)
+    }
+
+    /// Classifies an error returned by SurrealDB, which is `otherwise` unless it comes from the
+    /// connection to the database.
+    fn of_surrealdb(error: &surrealdb::Error, otherwise: ErrorKind) -> ErrorKind {
+        match error {
+            surrealdb::Error::Db(surrealdb::err::Error::Io(error))
+                if error.kind() == io::ErrorKind::TimedOut =>
+            {
+                ErrorKind::Timeout
+            }
+            error if is_connection_error(error) => ErrorKind::Network,
+            _ => otherwise,
+        }
+    }
 }
 
 impl Display for ConnectionError {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "ConnectionError: {}", self.error)
+        match &self.context {
+            Some(context) => write!(f, "ConnectionError: Cannot connect to {}", context),
+            None => write!(f, "ConnectionError: Cannot connect"),
+        }
     }
 }
 
 impl Error for ConnectionError {
-fn description(&self) -> &str {
-    self.error.as_str()
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<DatabaseConnectionErrors> for ConnectionError {
+    fn from(error: DatabaseConnectionErrors) -> Self {
+        ConnectionError {
+            error,
+            context: None,
+        }
+    }
 }
+
+/// The parts of the settings that identify a database, without credentials, so they are safe to
+/// log alongside an error.
+#[derive(Clone, Debug)]
+pub struct ConnectionContext {
+    /// The URL scheme of the database type, such as `ws` or `mem`.
+    pub engine: &'static str,
+    /// The host and port of a networked database, the placement driver endpoints of a TiKV
+    /// cluster, or the path of an embedded one.
+    pub address: String,
+    pub namespace: String,
+    pub database_name: String,
+}
+
+impl From<&DatabaseSettings> for ConnectionContext {
+    fn from(settings: &DatabaseSettings) -> Self {
+        let endpoints = settings
+            .cluster
+            .as_ref()
+            .map_or(&[][..], |cluster| &cluster.pd_endpoints[..]);
+        let address = if !endpoints.is_empty() {
+            endpoints.join(",")
+        } else if settings.db_type.is_networked() {
+            settings.address()
+        } else {
+            settings.path.clone()
+        };
+        ConnectionContext {
+            engine: settings.db_type.scheme(),
+            address,
+            namespace: settings.namespace.clone(),
+            database_name: settings.database_name.clone(),
+        }
+    }
+}
+
+impl Display for ConnectionContext {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(
+            f,
+            "{}://{} namespace `{}` database `{}`",
+            self.engine, self.address, self.namespace, self.database_name
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -23,18 +145,90 @@ pub enum DatabaseConnectionErrors {
     Default,
     InvalidNamespace,
     InvalidDatabaseName,
-    InvalidHost,
-    InvalidPort,
+    /// The host is not set, or is not an IP address or hostname. Holds the host.
+    InvalidHost(String),
+    /// The port is not a number from 0 to 65535. Holds the port as it was given.
+    InvalidPort(String),
+    /// An embedded datastore that is stored on disk was configured without a path.
+    InvalidPath,
     InvalidUsername,
     InvalidPassword,
-    HealthCheckFailed,
+    /// A record authentication was configured without an access method.
+    InvalidAccessMethod,
+    /// A token authentication was configured with an empty token.
+    InvalidToken,
+    /// A pooled connection failed its health check. Holds the name of the check and why it failed.
+    HealthCheckFailed {
+        check: &'static str,
+        reason: String,
+        kind: ErrorKind,
+    },
     NotYetImplemented,
     PoolConnectionError,
+    /// The database engine could not be reached or opened.
+    ConnectFailed(Box<surrealdb::Error>),
+    /// The namespace or database could not be selected.
+    NamespaceSelectionFailed(Box<surrealdb::Error>),
+    /// The credentials were rejected by the database.
+    AuthenticationFailed(Box<surrealdb::Error>),
+    /// The TLS settings could not be loaded or are inconsistent.
+    InvalidTlsConfiguration(Box<dyn Error + Send + Sync>),
+    /// The `on_connect` hook failed on a new connection.
+    OnConnectFailed(Box<dyn Error + Send + Sync>),
+    /// The session of a pooled connection could not be restored before it was handed out.
+    SessionResetFailed(Box<surrealdb::Error>),
+    /// The datastore could not be opened through any of the cluster endpoints. Holds the endpoints
+    /// that were tried.
+    UnreachableEndpoints(Vec<String>),
+    /// A connection URL could not be parsed.
+    InvalidUrl(url::ParseError),
+    /// A connection URL uses a scheme that is unknown or whose feature is not enabled.
+    UnsupportedScheme(String),
+    /// A connection URL has a query parameter that is unknown or has an invalid value.
+    InvalidUrlParameter(String),
+    /// A required environment variable is not set. Holds the name of the variable.
+    MissingEnvironmentVariable(String),
+    /// An environment variable is set to a value that cannot be used.
+    InvalidEnvironmentVariable {
+        name: String,
+        reason: String,
+    },
 }
 
 impl Display for DatabaseConnectionErrors {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "DatabaseConnectionError: {}", self)
+        match self {
+            DatabaseConnectionErrors::UnsupportedScheme(detail)
+            | DatabaseConnectionErrors::InvalidUrlParameter(detail)
+            | DatabaseConnectionErrors::MissingEnvironmentVariable(detail) => {
+                write!(f, "DatabaseConnectionError: {}: {}", self.as_str(), detail)
+            }
+            DatabaseConnectionErrors::UnreachableEndpoints(endpoints) => write!(
+                f,
+                "DatabaseConnectionError: {}: {}",
+                self.as_str(),
+                endpoints.join(", ")
+            ),
+            DatabaseConnectionErrors::InvalidHost(value)
+            | DatabaseConnectionErrors::InvalidPort(value) => {
+                write!(f, "DatabaseConnectionError: {}: `{}`", self.as_str(), value)
+            }
+            DatabaseConnectionErrors::InvalidEnvironmentVariable { name, reason } => write!(
+                f,
+                "DatabaseConnectionError: {}: {}: {}",
+                self.as_str(),
+                name,
+                reason
+            ),
+            DatabaseConnectionErrors::HealthCheckFailed { check, reason, .. } => write!(
+                f,
+                "DatabaseConnectionError: {}: {} check: {}",
+                self.as_str(),
+                check,
+                reason
+            ),
+            _ => write!(f, "DatabaseConnectionError: {}", self.as_str()),
+        }
     }
 }
 
@@ -42,6 +236,55 @@ impl DatabaseConnectionErrors {
     pub fn as_str(&self) -> &str {
         self.into()
     }
+
+    /// What kind of failure this is. See `ErrorKind`.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DatabaseConnectionErrors::ConnectFailed(error) => {
+                ErrorKind::of_surrealdb(error, ErrorKind::Server)
+            }
+            DatabaseConnectionErrors::NamespaceSelectionFailed(error) => {
+                ErrorKind::of_surrealdb(error, ErrorKind::Server)
+            }
+            DatabaseConnectionErrors::AuthenticationFailed(error) => {
+                ErrorKind::of_surrealdb(error, ErrorKind::Auth)
+            }
+            DatabaseConnectionErrors::SessionResetFailed(error) => {
+                ErrorKind::of_surrealdb(error, ErrorKind::Server)
+            }
+            DatabaseConnectionErrors::HealthCheckFailed { kind, .. } => *kind,
+            DatabaseConnectionErrors::OnConnectFailed(error) => error
+                .downcast_ref::<surrealdb::Error>()
+                .map_or(ErrorKind::Server, |error| {
+                    ErrorKind::of_surrealdb(error, ErrorKind::Server)
+                }),
+            DatabaseConnectionErrors::UnreachableEndpoints(_) => ErrorKind::Network,
+            DatabaseConnectionErrors::Default | DatabaseConnectionErrors::PoolConnectionError => {
+                ErrorKind::Server
+            }
+            DatabaseConnectionErrors::InvalidNamespace
+            | DatabaseConnectionErrors::InvalidDatabaseName
+            | DatabaseConnectionErrors::InvalidHost(_)
+            | DatabaseConnectionErrors::InvalidPort(_)
+            | DatabaseConnectionErrors::InvalidPath
+            | DatabaseConnectionErrors::InvalidUsername
+            | DatabaseConnectionErrors::InvalidPassword
+            | DatabaseConnectionErrors::InvalidAccessMethod
+            | DatabaseConnectionErrors::InvalidToken
+            | DatabaseConnectionErrors::NotYetImplemented
+            | DatabaseConnectionErrors::InvalidTlsConfiguration(_)
+            | DatabaseConnectionErrors::InvalidUrl(_)
+            | DatabaseConnectionErrors::UnsupportedScheme(_)
+            | DatabaseConnectionErrors::InvalidUrlParameter(_)
+            | DatabaseConnectionErrors::MissingEnvironmentVariable(_)
+            | DatabaseConnectionErrors::InvalidEnvironmentVariable { .. } => ErrorKind::Config,
+        }
+    }
+
+    /// Whether the failure is transient, so that trying again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
 }
 
 impl From<&DatabaseConnectionErrors> for &str {
@@ -50,23 +293,272 @@ impl From<&DatabaseConnectionErrors> for &str {
             DatabaseConnectionErrors::Default => "Database error",
             DatabaseConnectionErrors::InvalidNamespace => "Namespace not set error",
             DatabaseConnectionErrors::InvalidDatabaseName => "Database Name not set error",
-            DatabaseConnectionErrors::InvalidHost => "Host not set error",
-            DatabaseConnectionErrors::InvalidPort => {
-                "Port not set error: Port must be greater than 1024"
-            }
+            DatabaseConnectionErrors::InvalidHost(_) => "Invalid host error",
+            DatabaseConnectionErrors::InvalidPort(_) => "Invalid port error",
+            DatabaseConnectionErrors::InvalidPath => "Path not set error",
             DatabaseConnectionErrors::InvalidUsername => "Username not set error",
             DatabaseConnectionErrors::InvalidPassword => "Password not set error",
-            DatabaseConnectionErrors::HealthCheckFailed => "Health check failed error",
+            DatabaseConnectionErrors::InvalidAccessMethod => "Access method not set error",
+            DatabaseConnectionErrors::InvalidToken => "Token not set error",
+            DatabaseConnectionErrors::HealthCheckFailed { .. } => "Health check failed error",
             DatabaseConnectionErrors::NotYetImplemented => {
                 "Connection type not yet implemented error"
             }
             DatabaseConnectionErrors::PoolConnectionError => "Pool connection error",
+            DatabaseConnectionErrors::ConnectFailed(_) => "Database connect error",
+            DatabaseConnectionErrors::NamespaceSelectionFailed(_) => {
+                "Namespace or Database selection error"
+            }
+            DatabaseConnectionErrors::AuthenticationFailed(_) => "Authentication error",
+            DatabaseConnectionErrors::InvalidTlsConfiguration(_) => "TLS configuration error",
+            DatabaseConnectionErrors::OnConnectFailed(_) => "On connect hook error",
+            DatabaseConnectionErrors::SessionResetFailed(_) => "Session reset error",
+            DatabaseConnectionErrors::UnreachableEndpoints(_) => {
+                "No cluster endpoint reachable error"
+            }
+            DatabaseConnectionErrors::InvalidUrl(_) => "Connection URL parse error",
+            DatabaseConnectionErrors::UnsupportedScheme(_) => "Unsupported connection URL scheme",
+            DatabaseConnectionErrors::InvalidUrlParameter(_) => "Invalid connection URL parameter",
+            DatabaseConnectionErrors::MissingEnvironmentVariable(_) => {
+                "Environment variable not set error"
+            }
+            DatabaseConnectionErrors::InvalidEnvironmentVariable { .. } => {
+                "Invalid environment variable"
+            }
         }
     }
 }
 
 impl Error for DatabaseConnectionErrors {
-    fn description(&self) -> &str {
-        self.as_str()
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatabaseConnectionErrors::ConnectFailed(error)
+            | DatabaseConnectionErrors::NamespaceSelectionFailed(error)
+            | DatabaseConnectionErrors::AuthenticationFailed(error)
+            | DatabaseConnectionErrors::SessionResetFailed(error) => Some(&**error),
+            DatabaseConnectionErrors::InvalidTlsConfiguration(error)
+            | DatabaseConnectionErrors::OnConnectFailed(error) => Some(error.as_ref()),
+            DatabaseConnectionErrors::InvalidUrl(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned by `Migrator`.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// A connection could not be taken from the pool.
+    Pool(RunError<ConnectionError>),
+    /// A migration folder or file could not be read. Holds the path.
+    Io(PathBuf, io::Error),
+    /// A migration file is not named `<version>_<name>.surql`. Holds the file name.
+    InvalidFileName(String),
+    /// Two migrations have the same version.
+    DuplicateVersion(u64),
+    /// A migration was changed after it was applied.
+    ChecksumMismatch { version: u64, name: String },
+    /// The database records a migration as applied that is not among the known migrations.
+    UnknownVersion(u64),
+    /// A migration failed. `rolled_back` is set when the database reported an error in the
+    /// migration, so its transaction was cancelled. Otherwise the request itself failed, and
+    /// whether the migration was applied is not known.
+    Failed {
+        version: u64,
+        name: String,
+        rolled_back: bool,
+        error: Box<surrealdb::Error>,
+    },
+    /// The applied migrations could not be read from the database.
+    Query(Box<surrealdb::Error>),
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            MigrationError::Pool(_) => {
+                write!(f, "MigrationError: Cannot take a connection from the pool")
+            }
+            MigrationError::Io(path, _) => {
+                write!(f, "MigrationError: Cannot read {}", path.display())
+            }
+            MigrationError::InvalidFileName(name) => write!(
+                f,
+                "MigrationError: `{}` is not named `<version>_<name>.surql`",
+                name
+            ),
+            MigrationError::DuplicateVersion(version) => {
+                write!(
+                    f,
+                    "MigrationError: Version {} is given more than once",
+                    version
+                )
+            }
+            MigrationError::ChecksumMismatch { version, name } => write!(
+                f,
+                "MigrationError: Migration {} `{}` was changed after it was applied",
+                version, name
+            ),
+            MigrationError::UnknownVersion(version) => write!(
+                f,
+                "MigrationError: Applied migration {} is not known",
+                version
+            ),
+            MigrationError::Failed {
+                version,
+                name,
+                rolled_back: true,
+                ..
+            } => write!(
+                f,
+                "MigrationError: Migration {} `{}` failed and was rolled back",
+                version, name
+            ),
+            MigrationError::Failed { version, name, .. } => {
+                write!(f, "MigrationError: Migration {} `{}` failed", version, name)
+            }
+            MigrationError::Query(_) => {
+                write!(f, "MigrationError: Cannot read the applied migrations")
+            }
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::Pool(error) => Some(error),
+            MigrationError::Io(_, error) => Some(error),
+            MigrationError::Failed { error, .. } | MigrationError::Query(error) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned by `PoolTransaction::transaction`.
+#[derive(Debug)]
+pub enum TransactionError<E> {
+    /// A connection could not be taken from the pool.
+    Pool(RunError<ConnectionError>),
+    /// The closure returned an error, so the transaction was not sent.
+    Aborted(E),
+    /// The transaction failed, and its changes were rolled back. A write conflict is only
+    /// returned once the retries are used up.
+    Failed(surrealdb::Error),
+}
+
+impl<E> Display for TransactionError<E> {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            TransactionError::Pool(_) => {
+                write!(
+                    f,
+                    "TransactionError: Cannot take a connection from the pool"
+                )
+            }
+            TransactionError::Aborted(_) => write!(f, "TransactionError: Aborted"),
+            TransactionError::Failed(_) => {
+                write!(f, "TransactionError: Failed and was rolled back")
+            }
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for TransactionError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransactionError::Pool(error) => Some(error),
+            TransactionError::Aborted(error) => Some(error),
+            TransactionError::Failed(error) => Some(error),
+        }
+    }
+}
+
+/// The error returned by `LiveSubscription::subscribe`.
+#[derive(Debug)]
+pub enum LiveQueryError {
+    /// A connection could not be opened for the subscription.
+    Connection(ConnectionError),
+    /// The live query failed or did not return a stream.
+    Query(surrealdb::Error),
+}
+
+impl Display for LiveQueryError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            LiveQueryError::Connection(_) => {
+                write!(f, "LiveQueryError: Cannot open a connection")
+            }
+            LiveQueryError::Query(_) => write!(f, "LiveQueryError: The live query failed"),
+        }
+    }
+}
+
+impl Error for LiveQueryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LiveQueryError::Connection(error) => Some(error),
+            LiveQueryError::Query(error) => Some(error),
+        }
+    }
+}
+
+/// The error returned by `Repository`.
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// A connection could not be taken from the pool.
+    Pool(RunError<ConnectionError>),
+    /// The query failed, or its result did not match the record type.
+    Query(surrealdb::Error),
+    /// The database did not return the record that was created or upserted. `id` is `None` for a
+    /// record created with a generated id.
+    NotReturned {
+        table: String,
+        id: Option<RecordIdKey>,
+    },
+}
+
+impl Display for RepositoryError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            RepositoryError::Pool(_) => {
+                write!(f, "RepositoryError: Cannot take a connection from the pool")
+            }
+            RepositoryError::Query(_) => write!(f, "RepositoryError: The query failed"),
+            RepositoryError::NotReturned {
+                table,
+                id: Some(id),
+            } => write!(
+                f,
+                "RepositoryError: Record {}:{} was not returned",
+                table, id
+            ),
+            RepositoryError::NotReturned { table, id: None } => write!(
+                f,
+                "RepositoryError: Record created in {} was not returned",
+                table
+            ),
+        }
+    }
+}
+
+impl Error for RepositoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RepositoryError::Pool(error) => Some(error),
+            RepositoryError::Query(error) => Some(error),
+            RepositoryError::NotReturned { .. } => None,
+        }
+    }
+}
+
+impl From<RunError<ConnectionError>> for RepositoryError {
+    fn from(error: RunError<ConnectionError>) -> Self {
+        RepositoryError::Pool(error)
+    }
+}
+
+impl From<surrealdb::Error> for RepositoryError {
+    fn from(error: surrealdb::Error) -> Self {
+        RepositoryError::Query(error)
     }
 }
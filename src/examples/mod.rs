@@ -1,12 +1,10 @@
-use crate::{ConnectionManager, DatabaseSettings};
+use crate::errors::ConnectionError;
+use crate::{global_pool, ConnectionManager, DatabaseSettings};
 use bb8::Pool;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
-use std::sync::Arc;
 use surrealdb::sql::Thing;
-use tokio::sync::OnceCell;
 
 mod contactdb;
 mod rest;
@@ -45,21 +43,15 @@ impl Display for Contact {
     }
 }
 
-// Hold a single reference to the pool for all test cases to run in parallel.
-static POOL: OnceCell<Arc<Pool<ConnectionManager>>> = OnceCell::const_new();
-
-// Initialize the pool with a memory database.
-async fn init_pool() -> Result<Arc<Pool<ConnectionManager>>, Box<dyn Error>> {
-    let settings = DatabaseSettings::new_memory_db("test".to_string(), "test".to_string());
-    let manager = ConnectionManager::new(settings);
-    // Memory databases are single-threaded, so limit the pool size to 1.
-    let pool = Pool::builder().max_size(1).build(manager).await?;
-    Ok(Arc::new(pool))
-}
-
-/// Create a pool if it doesn't already exist and return a reference.
-pub async fn get_pool() -> Result<Arc<Pool<ConnectionManager>>, Box<dyn Error>> {
-    POOL.get_or_try_init(init_pool)
-        .await
-        .map(|pool| pool.clone())
+/// Returns the pool shared by the examples, creating it with a memory database on first use.
+/// Every pooled connection shares the same memory datastore, so records written through one
+/// connection are visible through all of the others.
+pub async fn get_pool() -> Result<&'static Pool<ConnectionManager>, ConnectionError> {
+    global_pool(|| async {
+        Ok(DatabaseSettings::new_memory_db(
+            "test".to_string(),
+            "test".to_string(),
+        ))
+    })
+    .await
 }
@@ -0,0 +1,134 @@
+use crate::connection::{is_connection_error, SurrealConnection};
+use crate::errors::{DatabaseConnectionErrors, ErrorKind};
+use std::error::Error;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+
+/// The error returned by a custom health check.
+pub type HealthCheckError = Box<dyn Error + Send + Sync>;
+
+type CustomCheck = dyn Fn(Surreal<Any>) -> Pin<Box<dyn Future<Output = Result<(), HealthCheckError>> + Send>>
+    + Send
+    + Sync;
+
+/// How the pool checks that a connection still works before handing it out.
+///
+/// Every check is cut short by the manager's health check timeout, and a connection that is
+/// known to be broken fails without a round trip.
+#[derive(Clone, Default)]
+pub enum HealthCheck {
+    /// Pings the server. Embedded engines always answer, so this only catches dead remote
+    /// connections.
+    #[default]
+    Ping,
+    /// Runs `RETURN 1`, which checks that queries are executed on the connection.
+    Query,
+    /// Runs `INFO FOR DB`, which checks that the namespace and database still exist and that
+    /// the signed in user may still use them.
+    Info,
+    /// Runs a user supplied check on a handle to the connection.
+    Custom(Arc<CustomCheck>),
+}
+
+impl HealthCheck {
+    /// Creates a check from an async closure, which is given a handle to the connection.
+    /// Returning a `surrealdb::Error` from the connection itself also marks it as broken.
+    pub fn custom<F, Fut>(check: F) -> Self
+    where
+        F: Fn(Surreal<Any>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HealthCheckError>> + Send + 'static,
+    {
+        HealthCheck::Custom(Arc::new(move |client| Box::pin(check(client))))
+    }
+
+    /// The name the check is reported by in `HealthCheckFailed`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HealthCheck::Ping => "ping",
+            HealthCheck::Query => "query",
+            HealthCheck::Info => "info",
+            HealthCheck::Custom(_) => "custom",
+        }
+    }
+
+    /// Runs the check. The caller bounds it with the health check timeout, reporting
+    /// `timed_out` when it does not finish in time.
+    pub(crate) async fn run(
+        &self,
+        conn: &SurrealConnection,
+    ) -> Result<(), DatabaseConnectionErrors> {
+        // A connection that is known to be broken would only hang waiting for a reconnect.
+        if conn.is_broken() {
+            return Err(self.failed("the connection is broken".to_string(), ErrorKind::Network));
+        }
+        match self.check(conn).await {
+            Ok(()) => Ok(()),
+            // A check that lost the connection has marked it as broken.
+            Err(error) if conn.is_broken() => {
+                Err(self.failed(error.to_string(), ErrorKind::Network))
+            }
+            Err(error) => Err(self.failed(error.to_string(), ErrorKind::Server)),
+        }
+    }
+
+    /// The error for a check that did not finish within the timeout.
+    pub(crate) fn timed_out(&self, timeout: Duration) -> DatabaseConnectionErrors {
+        self.failed(
+            format!("no answer within {:?}", timeout),
+            ErrorKind::Timeout,
+        )
+    }
+
+    async fn check(&self, conn: &SurrealConnection) -> Result<(), HealthCheckError> {
+        match self {
+            HealthCheck::Ping => conn.observe(conn.health().await)?,
+            HealthCheck::Query => {
+                let mut response = conn.observe(conn.query("RETURN 1").await)?;
+                let value: Option<i64> = response.take(0)?;
+                if value != Some(1) {
+                    return Err(format!("`RETURN 1` returned {:?}", value).into());
+                }
+            }
+            HealthCheck::Info => {
+                conn.observe(conn.query("INFO FOR DB").await)?.check()?;
+            }
+            HealthCheck::Custom(check) => {
+                let result = check((**conn).clone()).await;
+                if let Err(error) = &result {
+                    if error
+                        .downcast_ref::<surrealdb::Error>()
+                        .is_some_and(is_connection_error)
+                    {
+                        conn.mark_broken();
+                    }
+                }
+                result?
+            }
+        }
+        Ok(())
+    }
+
+    fn failed(&self, reason: String, kind: ErrorKind) -> DatabaseConnectionErrors {
+        DatabaseConnectionErrors::HealthCheckFailed {
+            check: self.name(),
+            reason,
+            kind,
+        }
+    }
+}
+
+impl Debug for HealthCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HealthCheck::Ping => "Ping",
+            HealthCheck::Query => "Query",
+            HealthCheck::Info => "Info",
+            HealthCheck::Custom(_) => "Custom",
+        })
+    }
+}
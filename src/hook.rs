@@ -0,0 +1,16 @@
+use crate::DatabaseSettings;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+
+/// The error returned by an `on_connect` hook.
+pub type OnConnectError = Box<dyn Error + Send + Sync>;
+
+/// The future returned by an `on_connect` hook, which may borrow the connection and settings.
+pub type OnConnectFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(), OnConnectError>> + Send + 'a>>;
+
+pub(crate) type OnConnect =
+    dyn for<'a> Fn(&'a Surreal<Any>, &'a DatabaseSettings) -> OnConnectFuture<'a> + Send + Sync;
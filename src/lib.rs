@@ -1,68 +1,239 @@
 extern crate bb8;
 extern crate surrealdb;
 
+use crate::auth::Authentication;
+use crate::cluster::ClusterSettings;
+use crate::connection::SurrealConnection;
+#[cfg(any(
+    feature = "kv-memory",
+    feature = "kv-rocksdb",
+    feature = "kv-surreal",
+    feature = "kv-tikv",
+    feature = "kv-fdb"
+))]
+use crate::embedded::EmbeddedDatastore;
 use crate::errors::{ConnectionError, DatabaseConnectionErrors};
+use crate::health::HealthCheck;
+use crate::hook::{OnConnect, OnConnectFuture};
+use crate::retry::Backoff;
+use crate::session::SessionReset;
+use crate::tls::TlsSettings;
 use async_trait::async_trait;
 use bb8::ManageConnection;
+use serde::{Deserialize, Serialize};
+#[cfg(any(
+    feature = "kv-memory",
+    feature = "kv-rocksdb",
+    feature = "kv-surreal",
+    feature = "kv-tikv",
+    feature = "kv-fdb"
+))]
+use std::future::Future;
+#[cfg(feature = "kv-fdb")]
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+#[allow(unused_imports)]
 use surrealdb::engine::any;
+#[allow(unused_imports)]
 use surrealdb::engine::any::Any;
 #[allow(unused_imports)]
-use surrealdb::opt::auth::Root;
+use surrealdb::opt::Config;
+#[allow(unused_imports)]
 use surrealdb::Surreal;
+#[cfg(any(
+    feature = "kv-memory",
+    feature = "kv-rocksdb",
+    feature = "kv-surreal",
+    feature = "kv-tikv",
+    feature = "kv-fdb"
+))]
+use tokio::sync::OnceCell;
+use tokio::time;
 
+mod address;
+pub mod auth;
+pub mod builder;
+pub mod cluster;
+pub mod connection;
+mod dsn;
+#[cfg(any(
+    feature = "kv-memory",
+    feature = "kv-rocksdb",
+    feature = "kv-surreal",
+    feature = "kv-tikv",
+    feature = "kv-fdb"
+))]
+mod embedded;
+mod env;
 pub mod errors;
+pub mod health;
+pub mod hook;
+pub mod live;
+pub mod migrations;
+pub mod pool;
+pub mod repository;
+pub mod retry;
+pub mod session;
+pub mod tenant;
+pub mod tls;
+pub mod transaction;
 
 #[cfg(feature = "examples")]
 pub mod examples;
 
+pub use pool::{build_pool, global_pool};
+
 /// Database types supported by the SurrealDB engine using the 'any::connect' method.
 /// See SurrealDB Rust API engine::any::connect for more details.
 /// Note that while they are included in this enum and the features for completeness,
 /// most of them are not yet implemented / verified within this library.
-#[derive(Debug)]
+/// Serialized as the URL scheme of the database type, such as `"ws"` or `"rocksdb"`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DatabaseType {
     // mem://
     #[cfg(feature = "kv-memory")]
+    #[serde(rename = "mem", alias = "memory", alias = "Memory")]
     Memory,
     // rocksdb://path/to/database-folder
     #[cfg(feature = "kv-rocksdb")]
+    #[serde(rename = "rocksdb", alias = "File")]
     File,
     // surrealkv://path/to/database-folder
     #[cfg(feature = "kv-surreal")]
+    #[serde(rename = "surrealkv", alias = "KeyValue")]
     KeyValue,
+    // surrealkv+versioned://path/to/database-folder
+    #[cfg(feature = "kv-surreal")]
+    #[serde(rename = "surrealkv+versioned", alias = "VersionedKeyValue")]
+    VersionedKeyValue,
     // ws://localhost:8000
     #[cfg(feature = "kv-websocket")]
+    #[serde(rename = "ws", alias = "WebSocket")]
     WebSocket,
     // wss://cloud.surrealdb.com
     #[cfg(feature = "kv-websocket")]
+    #[serde(rename = "wss", alias = "WebSocketSecure")]
     WebSocketSecure,
     // http://localhost:8000
     #[cfg(feature = "http")]
+    #[serde(rename = "http", alias = "Http")]
     Http,
     // https://cloud.surrealdb.com
     #[cfg(feature = "http")]
+    #[serde(rename = "https", alias = "Https")]
     Https,
     // Indxdb://DatabaseName
     #[cfg(feature = "kv-indxdb")]
+    #[serde(rename = "indxdb", alias = "Indxdb")]
     Indxdb,
     // tikv://localhost:2379
     #[cfg(feature = "kv-tikv")]
+    #[serde(rename = "tikv", alias = "TiKV")]
     TiKV,
     // fdb://path/to/fdb.cluster
     #[cfg(feature = "kv-fdb")]
+    #[serde(rename = "fdb", alias = "FoundationDB")]
     FoundationDB,
 }
 
-#[derive(Debug)]
+impl DatabaseType {
+    /// The URL scheme used to connect to this database type.
+    pub fn scheme(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "kv-memory")]
+            DatabaseType::Memory => "mem",
+            #[cfg(feature = "kv-rocksdb")]
+            DatabaseType::File => "rocksdb",
+            #[cfg(feature = "kv-surreal")]
+            DatabaseType::KeyValue => "surrealkv",
+            #[cfg(feature = "kv-surreal")]
+            DatabaseType::VersionedKeyValue => "surrealkv+versioned",
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocket => "ws",
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocketSecure => "wss",
+            #[cfg(feature = "http")]
+            DatabaseType::Http => "http",
+            #[cfg(feature = "http")]
+            DatabaseType::Https => "https",
+            #[cfg(feature = "kv-indxdb")]
+            DatabaseType::Indxdb => "indxdb",
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => "tikv",
+            #[cfg(feature = "kv-fdb")]
+            DatabaseType::FoundationDB => "fdb",
+        }
+    }
+
+    /// The database type for a URL scheme, if its feature is enabled.
+    pub fn from_scheme(scheme: &str) -> Option<Self> {
+        match scheme {
+            #[cfg(feature = "kv-memory")]
+            "mem" | "memory" => Some(DatabaseType::Memory),
+            #[cfg(feature = "kv-rocksdb")]
+            "rocksdb" => Some(DatabaseType::File),
+            #[cfg(feature = "kv-surreal")]
+            "surrealkv" => Some(DatabaseType::KeyValue),
+            #[cfg(feature = "kv-surreal")]
+            "surrealkv+versioned" => Some(DatabaseType::VersionedKeyValue),
+            #[cfg(feature = "kv-websocket")]
+            "ws" => Some(DatabaseType::WebSocket),
+            #[cfg(feature = "kv-websocket")]
+            "wss" => Some(DatabaseType::WebSocketSecure),
+            #[cfg(feature = "http")]
+            "http" => Some(DatabaseType::Http),
+            #[cfg(feature = "http")]
+            "https" => Some(DatabaseType::Https),
+            #[cfg(feature = "kv-indxdb")]
+            "indxdb" => Some(DatabaseType::Indxdb),
+            #[cfg(feature = "kv-tikv")]
+            "tikv" => Some(DatabaseType::TiKV),
+            #[cfg(feature = "kv-fdb")]
+            "fdb" => Some(DatabaseType::FoundationDB),
+            _ => None,
+        }
+    }
+
+    /// Whether this database type is addressed by host and port rather than by a path.
+    pub fn is_networked(&self) -> bool {
+        match *self {
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocket | DatabaseType::WebSocketSecure => true,
+            #[cfg(feature = "http")]
+            DatabaseType::Http | DatabaseType::Https => true,
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+/// Settings are deserialized from any serde format, so they can be read from config files.
+/// Only the database type, namespace and database name are required.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DatabaseSettings {
     pub db_type: DatabaseType,
-    pub username: String,
-    pub password: String,
+    /// How remote connections sign in. Embedded engines ignore this.
+    #[serde(default)]
+    pub auth: Authentication,
+    /// The port of a networked database. Zero selects the default port of the database type.
+    #[serde(default)]
     pub port: u16,
+    #[serde(default)]
     pub host: String,
+    #[serde(default)]
     pub path: String,
     pub namespace: String,
     pub database_name: String,
+    /// TLS options for `WebSocketSecure` and `Https` connections.
+    /// When `None`, the server certificate is verified against the web PKI roots.
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+    /// Placement driver endpoints and transaction timeouts for `TiKV` and `FoundationDB`.
+    #[serde(default)]
+    pub cluster: Option<ClusterSettings>,
 }
 
 impl DatabaseSettings {
@@ -70,135 +241,447 @@ impl DatabaseSettings {
     pub fn new_memory_db(namespace: String, database_name: String) -> Self {
         DatabaseSettings {
             db_type: DatabaseType::Memory,
-            username: "".to_string(),
-            password: "".to_string(),
+            auth: Authentication::None,
             port: 0,
             host: "".to_string(),
             path: "".to_string(),
             namespace,
             database_name,
+            tls: None,
+            cluster: None,
         }
     }
 
+    /// Settings for a RocksDB datastore in the given folder. RocksDB locks the folder, so the
+    /// datastore is opened once and shared by every connection in the pool.
     #[cfg(feature = "kv-rocksdb")]
     pub fn new_file_db(path: String, namespace: String, database_name: String) -> Self {
         DatabaseSettings {
             db_type: DatabaseType::File,
-            username: "".to_string(),
-            password: "".to_string(),
+            auth: Authentication::None,
             port: 0,
             host: "".to_string(),
             path,
             namespace,
             database_name,
+            tls: None,
+            cluster: None,
+        }
+    }
+
+    /// Settings for a SurrealKV datastore in the given folder. A versioned datastore keeps every
+    /// version of its records, so they can be read as they were at a point in time with
+    /// `SELECT ... VERSION d'...'`.
+    #[cfg(feature = "kv-surreal")]
+    pub fn new_surrealkv_db(
+        path: String,
+        namespace: String,
+        database_name: String,
+        versioned: bool,
+    ) -> Self {
+        DatabaseSettings {
+            db_type: if versioned {
+                DatabaseType::VersionedKeyValue
+            } else {
+                DatabaseType::KeyValue
+            },
+            auth: Authentication::None,
+            port: 0,
+            host: "".to_string(),
+            path,
+            namespace,
+            database_name,
+            tls: None,
+            cluster: None,
         }
     }
 }
 
+impl DatabaseSettings {
+    /// Checks the settings required by the database type, returning the first one that is
+    /// missing or invalid.
+    pub(crate) fn invalid_setting(&self) -> Option<DatabaseConnectionErrors> {
+        if self.namespace.is_empty() {
+            return Some(DatabaseConnectionErrors::InvalidNamespace);
+        }
+        if self.database_name.is_empty() {
+            return Some(DatabaseConnectionErrors::InvalidDatabaseName);
+        }
+        match self.db_type {
+            #[cfg(feature = "kv-rocksdb")]
+            DatabaseType::File => self.invalid_path(),
+            #[cfg(feature = "kv-surreal")]
+            DatabaseType::KeyValue | DatabaseType::VersionedKeyValue => self.invalid_path(),
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => {
+                let has_endpoints = self
+                    .cluster
+                    .as_ref()
+                    .is_some_and(|cluster| !cluster.pd_endpoints.is_empty());
+                if !has_endpoints && !address::is_valid_host(&self.host) {
+                    Some(DatabaseConnectionErrors::InvalidHost(self.host.clone()))
+                } else {
+                    None
+                }
+            }
+            // An empty path selects the default cluster file of the FoundationDB client.
+            #[cfg(feature = "kv-fdb")]
+            DatabaseType::FoundationDB => {
+                if !self.path.is_empty() && !Path::new(&self.path).is_file() {
+                    Some(DatabaseConnectionErrors::InvalidPath)
+                } else {
+                    None
+                }
+            }
+            #[cfg(feature = "kv-websocket")]
+            DatabaseType::WebSocket | DatabaseType::WebSocketSecure => {
+                self.invalid_remote_setting()
+            }
+            #[cfg(feature = "http")]
+            DatabaseType::Http | DatabaseType::Https => self.invalid_remote_setting(),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    #[cfg(any(feature = "kv-rocksdb", feature = "kv-surreal"))]
+    fn invalid_path(&self) -> Option<DatabaseConnectionErrors> {
+        if self.path.is_empty() {
+            Some(DatabaseConnectionErrors::InvalidPath)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the settings required by engines that connect to a remote server,
+    /// returning the first one that is missing or invalid.
+    #[cfg(any(feature = "kv-websocket", feature = "http"))]
+    fn invalid_remote_setting(&self) -> Option<DatabaseConnectionErrors> {
+        if !address::is_valid_host(&self.host) {
+            Some(DatabaseConnectionErrors::InvalidHost(self.host.clone()))
+        } else {
+            self.auth.invalid_setting()
+        }
+    }
+
+    /// The host and port of a networked database, with the default port of the database type
+    /// when the port is zero.
+    pub(crate) fn address(&self) -> String {
+        let port = match self.port {
+            0 => self.db_type.default_port().unwrap_or(0),
+            port => port,
+        };
+        address::host_port(&self.host, port)
+    }
+}
+
+/// How often remote connections are pinged to check that they are still alive.
+const DEFAULT_LIVENESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a health check may take before the connection is considered unhealthy.
+const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct ConnectionManager {
     settings: DatabaseSettings,
+    liveness_interval: Duration,
+    health_check: HealthCheck,
+    health_check_timeout: Duration,
+    backoff: Backoff,
+    on_connect: Option<Arc<OnConnect>>,
+    session_reset: SessionReset,
+    /// The embedded datastore shared by every pooled connection for in-process engines.
+    /// Each pooled connection is a client of its own, so they see the same data from their own
+    /// sessions. Managers made with `for_settings` share it too.
+    #[cfg(any(
+        feature = "kv-memory",
+        feature = "kv-rocksdb",
+        feature = "kv-surreal",
+        feature = "kv-tikv",
+        feature = "kv-fdb"
+    ))]
+    embedded: Arc<OnceCell<EmbeddedDatastore>>,
 }
 
 impl ConnectionManager {
     pub fn new(settings: DatabaseSettings) -> Self {
-        ConnectionManager { settings }
+        ConnectionManager {
+            settings,
+            liveness_interval: DEFAULT_LIVENESS_INTERVAL,
+            health_check: HealthCheck::default(),
+            health_check_timeout: DEFAULT_HEALTH_CHECK_TIMEOUT,
+            backoff: Backoff::default(),
+            on_connect: None,
+            session_reset: SessionReset::default(),
+            #[cfg(any(
+                feature = "kv-memory",
+                feature = "kv-rocksdb",
+                feature = "kv-surreal",
+                feature = "kv-tikv",
+                feature = "kv-fdb"
+            ))]
+            embedded: Arc::new(OnceCell::new()),
+        }
     }
-}
 
-#[async_trait]
-impl ManageConnection for ConnectionManager {
-    /// The connection type this manager deals with.
-    type Connection = Surreal<Any>;
+    /// Creates a manager for other settings that opens its connections on the embedded
+    /// datastore of this one, so that several namespaces can be pooled on one datastore.
+    pub(crate) fn for_settings(&self, settings: DatabaseSettings) -> ConnectionManager {
+        ConnectionManager {
+            #[cfg(any(
+                feature = "kv-memory",
+                feature = "kv-rocksdb",
+                feature = "kv-surreal",
+                feature = "kv-tikv",
+                feature = "kv-fdb"
+            ))]
+            embedded: self.embedded.clone(),
+            ..ConnectionManager::new(settings)
+        }
+    }
 
-    /// The error type returned by `Connection`s.
-    type Error = ConnectionError;
+    /// Sets how often remote connections are pinged in the background. A connection whose ping
+    /// fails or is not answered within the interval is reported as broken, and is closed by the
+    /// pool instead of being reused.
+    pub fn with_liveness_interval(mut self, interval: Duration) -> Self {
+        self.liveness_interval = interval;
+        self
+    }
 
-    /// Connects to a local, remote or embedded database
-    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        if self.settings.namespace.is_empty() {
-            return Err(ConnectionError {
-                error: DatabaseConnectionErrors::InvalidNamespace,
-            });
+    /// Sets how connections are checked before the pool hands them out. Pings by default.
+    /// Checks only run when the pool is built with `test_on_check_out`, which is bb8's default.
+    pub fn with_health_check(mut self, health_check: HealthCheck) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    /// Sets how long a health check may take before the connection is discarded.
+    pub fn with_health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+
+    /// Sets how `connect` retries transient failures. See `Backoff` for the defaults.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets what is restored on a connection before the pool hands it out again. Everything is
+    /// restored by default. See `SessionReset`.
+    pub fn with_session_reset(mut self, session_reset: SessionReset) -> Self {
+        self.session_reset = session_reset;
+        self
+    }
+
+    /// Sets a hook that runs on every new connection before the pool hands it out, to set
+    /// session parameters or warm up the connection. An error from the hook fails the connection
+    /// with `OnConnectFailed`.
+    ///
+    /// ```ignore
+    /// let manager = ConnectionManager::new(settings).on_connect(|client, settings| {
+    ///     Box::pin(async move {
+    ///         client.set("tenant", settings.namespace.clone()).await?;
+    ///         Ok(())
+    ///     })
+    /// });
+    /// ```
+    pub fn on_connect<F>(mut self, hook: F) -> Self
+    where
+        F: for<'a> Fn(&'a Surreal<Any>, &'a DatabaseSettings) -> OnConnectFuture<'a>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.on_connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Connects and runs the `on_connect` hook on the new connection.
+    async fn open_session(&self) -> Result<SurrealConnection, ConnectionError> {
+        let conn = self.open_connection().await?;
+        if let Some(hook) = &self.on_connect {
+            if let Err(error) = hook(&conn, &self.settings).await {
+                return Err(DatabaseConnectionErrors::OnConnectFailed(error).into());
+            }
         }
-        if self.settings.database_name.is_empty() {
-            return Err(ConnectionError {
-                error: DatabaseConnectionErrors::InvalidDatabaseName,
-            });
+        Ok(conn)
+    }
+
+    /// Connects to the database described by the settings.
+    async fn open_connection(&self) -> Result<SurrealConnection, ConnectionError> {
+        if let Some(error) = self.settings.invalid_setting() {
+            return Err(error.into());
         }
         match self.settings.db_type {
             #[cfg(feature = "kv-memory")]
             DatabaseType::Memory => {
-                let conn: Surreal<Any> = any::connect("mem://").await.unwrap();
-                conn.use_ns(self.settings.namespace.as_str())
-                    .use_db(self.settings.database_name.as_str())
+                self.embedded_connection(EmbeddedDatastore::open("memory".to_string(), None))
                     .await
-                    .unwrap();
-                Ok(conn)
             }
+            // RocksDB and SurrealKV lock their folder, so the datastore is opened once and shared.
             #[cfg(feature = "kv-rocksdb")]
-            DatabaseType::File => {
-                let conn_str = format!("rocksdb://{}", self.settings.path);
-                let conn: Surreal<Any> = any::connect(conn_str).await.unwrap();
-                conn.use_ns(self.settings.namespace.as_str())
-                    .use_db(self.settings.database_name.as_str())
-                    .await
-                    .unwrap();
-                Ok(conn)
+            DatabaseType::File => self.file_connection().await,
+            #[cfg(feature = "kv-surreal")]
+            DatabaseType::KeyValue | DatabaseType::VersionedKeyValue => {
+                self.file_connection().await
             }
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => self.cluster_connection().await,
+            #[cfg(feature = "kv-fdb")]
+            DatabaseType::FoundationDB => self.cluster_connection().await,
             #[cfg(feature = "kv-websocket")]
-            DatabaseType::WebSocket => {
-                if self.settings.host.is_empty() {
-                    return Err(ConnectionError {
-                        error: DatabaseConnectionErrors::InvalidHost,
-                    });
-                }
-                if self.settings.port < 1025 {
-                    return Err(ConnectionError {
-                        error: DatabaseConnectionErrors::InvalidPort,
-                    });
-                }
-                if self.settings.username.is_empty() {
-                    return Err(ConnectionError {
-                        error: DatabaseConnectionErrors::InvalidUsername,
-                    });
-                }
-                if self.settings.password.is_empty() {
-                    return Err(ConnectionError {
-                        error: DatabaseConnectionErrors::InvalidPassword,
-                    });
-                }
-                let host = format!("ws://{}:{}", self.settings.host, self.settings.port);
-                let conn: Surreal<Any> = any::connect(host).await.unwrap();
-                conn.signin(Root {
-                    username: self.settings.username.as_str(),
-                    password: self.settings.password.as_str(),
-                })
-                .await
-                .unwrap();
-                Ok(conn)
+            DatabaseType::WebSocket | DatabaseType::WebSocketSecure => {
+                self.remote_connection().await
             }
+            #[cfg(feature = "http")]
+            DatabaseType::Http | DatabaseType::Https => self.remote_connection().await,
             // Reason: Some feature variants are not yet implemented, and this helps to provide
             // an error when features are enabled but variants are not yet supported.
             #[allow(unreachable_patterns)]
-            _ => Err(ConnectionError {
-                error: DatabaseConnectionErrors::NotYetImplemented,
-            }),
+            _ => Err(DatabaseConnectionErrors::NotYetImplemented.into()),
         }
     }
 
-    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        let health = conn.health().await.is_ok();
-        if !health {
-            Err(ConnectionError {
-                error: DatabaseConnectionErrors::HealthCheckFailed,
-            })
-        } else {
-            Ok(())
+    /// Opens the embedded datastore with `open` on first use and connects to it. Each connection
+    /// has a session of its own, which selects the namespace and database from the settings.
+    #[cfg(any(
+        feature = "kv-memory",
+        feature = "kv-rocksdb",
+        feature = "kv-surreal",
+        feature = "kv-tikv",
+        feature = "kv-fdb"
+    ))]
+    async fn embedded_connection(
+        &self,
+        open: impl Future<Output = Result<EmbeddedDatastore, DatabaseConnectionErrors>>,
+    ) -> Result<SurrealConnection, ConnectionError> {
+        let datastore = self.embedded.get_or_try_init(|| open).await?;
+        let conn = datastore.connect().await?;
+        conn.use_ns(self.settings.namespace.as_str())
+            .use_db(self.settings.database_name.as_str())
+            .await
+            .map_err(|e| DatabaseConnectionErrors::NamespaceSelectionFailed(Box::new(e)))?;
+        Ok(SurrealConnection::new(conn))
+    }
+
+    /// Opens the datastore stored in the folder at the settings path, shared like any other
+    /// embedded datastore.
+    #[cfg(any(feature = "kv-rocksdb", feature = "kv-surreal"))]
+    async fn file_connection(&self) -> Result<SurrealConnection, ConnectionError> {
+        let address = format!(
+            "{}://{}",
+            self.settings.db_type.scheme(),
+            self.settings.path
+        );
+        self.embedded_connection(EmbeddedDatastore::open(address, None))
+            .await
+    }
+
+    /// Opens the TiKV or FoundationDB datastore described by the settings. The cluster is
+    /// reached from within the process, so the datastore is shared like any embedded datastore.
+    #[cfg(any(feature = "kv-tikv", feature = "kv-fdb"))]
+    async fn cluster_connection(&self) -> Result<SurrealConnection, ConnectionError> {
+        let cluster = self.settings.cluster.clone().unwrap_or_default();
+        match self.settings.db_type {
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => {
+                let mut endpoints = cluster.pd_endpoints;
+                if endpoints.is_empty() {
+                    endpoints.push(self.settings.address());
+                }
+                let open = crate::cluster::open_tikv(endpoints, cluster.transaction_timeout);
+                self.embedded_connection(open).await
+            }
+            #[cfg(feature = "kv-fdb")]
+            DatabaseType::FoundationDB => {
+                let address = format!("fdb://{}", self.settings.path);
+                let open = EmbeddedDatastore::open(address, cluster.transaction_timeout);
+                self.embedded_connection(open).await
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(DatabaseConnectionErrors::NotYetImplemented.into()),
         }
     }
 
-    fn has_broken(&self, _: &mut Surreal<Any>) -> bool {
-        // TODO: Implement a proper check for non-memory cases
-        false
+    /// Connects to a remote server, signs in and selects the namespace and database.
+    #[cfg(any(feature = "kv-websocket", feature = "http"))]
+    async fn remote_connection(&self) -> Result<SurrealConnection, ConnectionError> {
+        let address = format!(
+            "{}://{}",
+            self.settings.db_type.scheme(),
+            self.settings.address()
+        );
+        let conn: Surreal<Any> = match &self.settings.tls {
+            Some(tls) => {
+                let config = tls
+                    .client_config()
+                    .map_err(DatabaseConnectionErrors::InvalidTlsConfiguration)?;
+                any::connect((address, Config::new().rustls(config))).await
+            }
+            None => any::connect(address).await,
+        }
+        .map_err(|e| DatabaseConnectionErrors::ConnectFailed(Box::new(e)))?;
+        self.settings
+            .auth
+            .apply(
+                &conn,
+                self.settings.namespace.as_str(),
+                self.settings.database_name.as_str(),
+            )
+            .await?;
+        conn.use_ns(self.settings.namespace.as_str())
+            .use_db(self.settings.database_name.as_str())
+            .await
+            .map_err(|e| DatabaseConnectionErrors::NamespaceSelectionFailed(Box::new(e)))?;
+        Ok(SurrealConnection::watched(conn, self.liveness_interval))
+    }
+}
+
+#[async_trait]
+impl ManageConnection for ConnectionManager {
+    /// The connection type this manager deals with.
+    type Connection = SurrealConnection;
+
+    /// The error type returned by `Connection`s.
+    type Error = ConnectionError;
+
+    /// Connects to a local, remote or embedded database
+    /// Transient failures are retried as set by `with_backoff`.
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let mut retry = 0;
+        loop {
+            match self.open_session().await {
+                Ok(conn) => return Ok(conn),
+                Err(error) if error.is_retryable() && retry < self.backoff.max_retries => {
+                    tokio::time::sleep(self.backoff.delay(retry)).await;
+                    retry += 1;
+                }
+                Err(error) => return Err(error.with_context(&self.settings)),
+            }
+        }
+    }
+
+    /// Restores the session as set by `with_session_reset`, then runs the health check, failing
+    /// when the two together do not finish within the health check timeout.
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        let conn = &*conn;
+        let checked = async {
+            // A connection that is known to be broken fails the health check without a round trip.
+            if !conn.is_broken() {
+                self.session_reset
+                    .run(conn, &self.settings, self.on_connect.as_deref())
+                    .await?;
+            }
+            self.health_check.run(conn).await
+        };
+        let result = match time::timeout(self.health_check_timeout, checked).await {
+            Ok(result) => result,
+            Err(_) => Err(self.health_check.timed_out(self.health_check_timeout)),
+        };
+        result.map_err(|error| ConnectionError::from(error).with_context(&self.settings))
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.is_broken()
     }
 }
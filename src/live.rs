@@ -0,0 +1,213 @@
+use crate::connection::SurrealConnection;
+use crate::errors::LiveQueryError;
+use crate::retry::Backoff;
+use crate::ConnectionManager;
+use bb8::Pool;
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use surrealdb::method::QueryStream;
+use surrealdb::Notification;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::AbortHandle;
+use tokio::time;
+
+/// How many events are held for a subscriber that is not keeping up.
+const EVENT_BUFFER: usize = 64;
+
+/// How often the connection of a subscription is checked for being broken.
+const BROKEN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An event of a `LiveSubscription`.
+#[derive(Debug)]
+pub enum LiveEvent<T> {
+    /// A record matched by the live query was created, updated or deleted.
+    Notification(Notification<T>),
+    /// The connection of the subscription was lost. Changes made until the next `Reconnected`
+    /// are not notified, so anything derived from the notifications should be reloaded then.
+    Disconnected,
+    /// The live query was subscribed again on a new connection.
+    Reconnected,
+}
+
+/// A `LIVE SELECT` that outlives the connection it runs on.
+///
+/// The live query runs on a dedicated connection that is opened through the pool but is not
+/// part of it, so it is not recycled by `max_lifetime` or `idle_timeout`. When the connection is
+/// lost, the subscription opens a new one and runs the live query again, waiting between
+/// attempts as set by its `Backoff`. The notifications are read as a `Stream`, which marks the
+/// gap with `Disconnected` and `Reconnected` events. Each failed attempt is passed on as an
+/// error, and the stream ends when the attempts run out.
+///
+/// Dropping the subscription kills the live query.
+///
+/// ```ignore
+/// let mut people = LiveSubscription::<Person>::subscribe(&pool, "LIVE SELECT * FROM person").await?;
+/// while let Some(event) = people.next().await {
+///     match event? {
+///         LiveEvent::Notification(notification) => println!("{:?}", notification.data),
+///         LiveEvent::Disconnected | LiveEvent::Reconnected => reload().await,
+///     }
+/// }
+/// ```
+pub struct LiveSubscription<T> {
+    events: mpsc::Receiver<Result<LiveEvent<T>, LiveQueryError>>,
+    reconnect: Arc<Notify>,
+    task: AbortHandle,
+}
+
+impl<T> LiveSubscription<T>
+where
+    T: DeserializeOwned + Unpin + Send + 'static,
+{
+    /// Runs the live query, which must be a single `LIVE SELECT` statement, on a dedicated
+    /// connection from the pool, subscribing again with the default `Backoff`. Fails when the
+    /// first connection cannot be opened or the query fails, so that mistakes in the query are
+    /// reported here rather than retried.
+    pub async fn subscribe(
+        pool: &Pool<ConnectionManager>,
+        query: impl Into<String>,
+    ) -> Result<Self, LiveQueryError> {
+        Self::subscribe_with(pool, query, &Backoff::default()).await
+    }
+
+    /// Runs the live query like `subscribe`, subscribing again as set by the given `Backoff`.
+    pub async fn subscribe_with(
+        pool: &Pool<ConnectionManager>,
+        query: impl Into<String>,
+        backoff: &Backoff,
+    ) -> Result<Self, LiveQueryError> {
+        let query = query.into();
+        let subscribed = subscribe(pool, &query).await?;
+        let (sender, events) = mpsc::channel(EVENT_BUFFER);
+        let reconnect = Arc::new(Notify::new());
+        let task = tokio::spawn(run(
+            pool.clone(),
+            query,
+            subscribed,
+            backoff.clone(),
+            sender,
+            reconnect.clone(),
+        ));
+        Ok(LiveSubscription {
+            events,
+            reconnect,
+            task: task.abort_handle(),
+        })
+    }
+}
+
+impl<T> LiveSubscription<T> {
+    /// Drops the connection and runs the live query again on a new one, as when the connection
+    /// is lost, such as to pick up changed credentials.
+    pub fn reconnect(&self) {
+        self.reconnect.notify_one();
+    }
+}
+
+impl<T> Stream for LiveSubscription<T> {
+    type Item = Result<LiveEvent<T>, LiveQueryError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for LiveSubscription<T> {
+    fn drop(&mut self) {
+        // The task holds the live query stream, which kills the live query when dropped.
+        self.task.abort();
+    }
+}
+
+type Subscribed<T> = (SurrealConnection, QueryStream<Notification<T>>);
+
+/// Opens a dedicated connection and runs the live query on it.
+async fn subscribe<T>(
+    pool: &Pool<ConnectionManager>,
+    query: &str,
+) -> Result<Subscribed<T>, LiveQueryError>
+where
+    T: DeserializeOwned + Unpin,
+{
+    let conn = pool
+        .dedicated_connection()
+        .await
+        .map_err(LiveQueryError::Connection)?;
+    let mut response = conn
+        .observe(conn.query(query).await)
+        .map_err(LiveQueryError::Query)?
+        .check()
+        .map_err(LiveQueryError::Query)?;
+    let stream = response.stream(0).map_err(LiveQueryError::Query)?;
+    Ok((conn, stream))
+}
+
+/// Passes the notifications on to the subscriber, and subscribes again whenever the connection is
+/// lost, until the subscriber is dropped or the attempts to subscribe again run out.
+async fn run<T>(
+    pool: Pool<ConnectionManager>,
+    query: String,
+    subscribed: Subscribed<T>,
+    backoff: Backoff,
+    sender: mpsc::Sender<Result<LiveEvent<T>, LiveQueryError>>,
+    reconnect: Arc<Notify>,
+) where
+    T: DeserializeOwned + Unpin + Send + 'static,
+{
+    let mut subscribed = Some(subscribed);
+    let mut retry = 0;
+    loop {
+        let (conn, mut stream) = match subscribed.take() {
+            Some(subscribed) => subscribed,
+            None => match subscribe(&pool, &query).await {
+                Ok(subscribed) => {
+                    retry = 0;
+                    if sender.send(Ok(LiveEvent::Reconnected)).await.is_err() {
+                        return;
+                    }
+                    subscribed
+                }
+                Err(error) => {
+                    if sender.send(Err(error)).await.is_err() || retry >= backoff.max_retries {
+                        return;
+                    }
+                    time::sleep(backoff.delay(retry)).await;
+                    retry += 1;
+                    continue;
+                }
+            },
+        };
+        let mut checks = time::interval(BROKEN_CHECK_INTERVAL);
+        loop {
+            let lost = tokio::select! {
+                notification = stream.next() => match notification {
+                    Some(notification) => {
+                        let event = notification
+                            .map(LiveEvent::Notification)
+                            .map_err(LiveQueryError::Query);
+                        if sender.send(event).await.is_err() {
+                            return;
+                        }
+                        false
+                    }
+                    None => true,
+                },
+                _ = checks.tick() => conn.is_broken(),
+                _ = reconnect.notified() => true,
+            };
+            if lost {
+                break;
+            }
+        }
+        // The stream holds a handle to the client, so it is dropped first to close the connection.
+        drop(stream);
+        drop(conn);
+        if sender.send(Ok(LiveEvent::Disconnected)).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::errors::MigrationError;
+use crate::ConnectionManager;
+use bb8::Pool;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+
+/// The table that records which migrations have been applied.
+pub const MIGRATIONS_TABLE: &str = "_migrations";
+
+/// A SurrealQL script that changes the schema, identified by its version.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub script: String,
+}
+
+impl Migration {
+    pub fn new(version: u64, name: impl Into<String>, script: impl Into<String>) -> Self {
+        Migration {
+            version,
+            name: name.into(),
+            script: script.into(),
+        }
+    }
+
+    /// The SHA-256 of the script, recorded when the migration is applied so that later changes
+    /// to the script are detected.
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.script.as_bytes()))
+    }
+}
+
+/// A migration recorded in the migrations table.
+#[derive(Debug, Deserialize)]
+struct AppliedMigration {
+    version: u64,
+    checksum: String,
+}
+
+/// Applies migrations in version order, recording each one in the `_migrations` table.
+///
+/// Each migration runs in a transaction together with its record, so a failed migration leaves
+/// neither its changes nor its record behind, and two processes migrating at once cannot both
+/// apply the same version. Scripts must therefore not begin or commit transactions themselves.
+///
+/// ```ignore
+/// let migrator = Migrator::from_dir("migrations")?;
+/// let applied = migrator.run(&pool).await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Migrator {
+    migrations: BTreeMap<u64, Migration>,
+}
+
+impl Migrator {
+    /// Creates a migrator from migrations given in any order, such as scripts embedded with
+    /// `include_str!`.
+    pub fn new(migrations: Vec<Migration>) -> Result<Self, MigrationError> {
+        let mut by_version = BTreeMap::new();
+        for migration in migrations {
+            let version = migration.version;
+            if by_version.insert(version, migration).is_some() {
+                return Err(MigrationError::DuplicateVersion(version));
+            }
+        }
+        Ok(Migrator {
+            migrations: by_version,
+        })
+    }
+
+    /// Reads the `.surql` files of a folder, which are named `<version>_<name>.surql`, such as
+    /// `0001_create_users.surql`. Other files are ignored.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, MigrationError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|error| MigrationError::Io(dir.into(), error))?;
+        let mut migrations = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|error| MigrationError::Io(dir.into(), error))?
+                .path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("surql") {
+                continue;
+            }
+            let file_name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let (version, name) = file_name
+                .trim_end_matches(".surql")
+                .split_once('_')
+                .and_then(|(version, name)| Some((version.parse().ok()?, name)))
+                .filter(|(_, name)| !name.is_empty())
+                .ok_or_else(|| MigrationError::InvalidFileName(file_name.clone()))?;
+            let script =
+                fs::read_to_string(&path).map_err(|error| MigrationError::Io(path, error))?;
+            migrations.push(Migration::new(version, name, script));
+        }
+        Self::new(migrations)
+    }
+
+    /// The migrations, in version order.
+    pub fn migrations(&self) -> impl Iterator<Item = &Migration> {
+        self.migrations.values()
+    }
+
+    /// Returns the migrations that `run` would apply, without applying them. Fails the same way
+    /// `run` does when an applied migration was changed or is not known.
+    pub async fn dry_run(
+        &self,
+        pool: &Pool<ConnectionManager>,
+    ) -> Result<Vec<&Migration>, MigrationError> {
+        let conn = pool.get().await.map_err(MigrationError::Pool)?;
+        self.pending(&conn).await
+    }
+
+    /// Applies the migrations that have not been applied yet, in version order, and returns
+    /// them. Stops at the first one that fails.
+    pub async fn run(
+        &self,
+        pool: &Pool<ConnectionManager>,
+    ) -> Result<Vec<&Migration>, MigrationError> {
+        let conn = pool.get().await.map_err(MigrationError::Pool)?;
+        let pending = self.pending(&conn).await?;
+        for migration in &pending {
+            apply(&conn, migration).await?;
+        }
+        Ok(pending)
+    }
+
+    /// Compares the known migrations with those recorded as applied, and returns the ones that
+    /// are not applied yet.
+    async fn pending(&self, conn: &Surreal<Any>) -> Result<Vec<&Migration>, MigrationError> {
+        let mut response = conn
+            .query("SELECT meta::id(id) AS version, checksum FROM type::table($table)")
+            .bind(("table", MIGRATIONS_TABLE))
+            .await
+            .map_err(|e| MigrationError::Query(Box::new(e)))?;
+        let applied: Vec<AppliedMigration> = response
+            .take(0)
+            .map_err(|e| MigrationError::Query(Box::new(e)))?;
+        let mut pending: BTreeSet<u64> = self.migrations.keys().copied().collect();
+        for record in applied {
+            let migration = self
+                .migrations
+                .get(&record.version)
+                .ok_or(MigrationError::UnknownVersion(record.version))?;
+            if migration.checksum() != record.checksum {
+                return Err(MigrationError::ChecksumMismatch {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                });
+            }
+            pending.remove(&record.version);
+        }
+        Ok(pending
+            .iter()
+            .filter_map(|version| self.migrations.get(version))
+            .collect())
+    }
+}
+
+/// Runs a migration and records it in one transaction.
+async fn apply(conn: &Surreal<Any>, migration: &Migration) -> Result<(), MigrationError> {
+    let query = format!(
+        "BEGIN TRANSACTION;\n{}\n;\
+         CREATE type::thing($migration_table, $migration_version) CONTENT {{ \
+         name: $migration_name, checksum: $migration_checksum, applied_at: time::now() }};\n\
+         COMMIT TRANSACTION;",
+        migration.script
+    );
+    let failed = |rolled_back| {
+        move |error| MigrationError::Failed {
+            version: migration.version,
+            name: migration.name.clone(),
+            rolled_back,
+            error: Box::new(error),
+        }
+    };
+    conn.query(query)
+        .bind(("migration_table", MIGRATIONS_TABLE))
+        .bind(("migration_version", migration.version))
+        .bind(("migration_name", migration.name.clone()))
+        .bind(("migration_checksum", migration.checksum()))
+        .await
+        // The request may have failed after the transaction was committed.
+        .map_err(failed(false))?
+        // A statement failed, which cancels the whole transaction.
+        .check()
+        .map_err(failed(true))?;
+    Ok(())
+}
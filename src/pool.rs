@@ -0,0 +1,117 @@
+use crate::errors::{ConnectionError, DatabaseConnectionErrors};
+use crate::{ConnectionManager, DatabaseSettings, DatabaseType};
+use bb8::{Builder, Pool};
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// The pool shared by the whole process, see `global_pool`.
+static GLOBAL_POOL: OnceCell<Pool<ConnectionManager>> = OnceCell::const_new();
+
+impl DatabaseType {
+    /// Whether the datastore runs inside this process and is shared by every pooled connection,
+    /// rather than being reached over a connection of its own.
+    pub fn is_embedded(&self) -> bool {
+        match *self {
+            #[cfg(feature = "kv-memory")]
+            DatabaseType::Memory => true,
+            #[cfg(feature = "kv-rocksdb")]
+            DatabaseType::File => true,
+            #[cfg(feature = "kv-surreal")]
+            DatabaseType::KeyValue | DatabaseType::VersionedKeyValue => true,
+            #[cfg(feature = "kv-indxdb")]
+            DatabaseType::Indxdb => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Whether the datastore is a TiKV or FoundationDB cluster reached from within this process.
+    fn is_cluster(&self) -> bool {
+        match *self {
+            #[cfg(feature = "kv-tikv")]
+            DatabaseType::TiKV => true,
+            #[cfg(feature = "kv-fdb")]
+            DatabaseType::FoundationDB => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+impl ConnectionManager {
+    /// A pool builder with defaults suited to the database type:
+    ///
+    /// * Embedded datastores hand out cheap handles to one shared datastore, so they get a small
+    ///   pool with a short connection timeout, and are only checked on checkout when a session
+    ///   reset is set with `with_session_reset`.
+    /// * TiKV and FoundationDB clusters are also shared in the process, but can take a while to
+    ///   reach, so they get a longer connection timeout and are checked on checkout.
+    /// * Remote servers get a larger pool, as each connection is a socket of its own, and are
+    ///   checked on checkout so that dead connections are not handed out.
+    ///
+    /// None of them let bb8 retry failed connects, as `connect` already retries transient errors
+    /// as set by `with_backoff`, and bb8 would otherwise retry configuration and authentication
+    /// errors too until the connection timeout. bb8 reports a connect that fails on checkout as
+    /// `RunError::TimedOut`, so use `build_pool` to have such errors returned when building.
+    ///
+    /// Any of these can be changed on the returned builder before calling `build`.
+    pub fn pool_builder(&self) -> Builder<ConnectionManager> {
+        let db_type = &self.settings.db_type;
+        let builder = Pool::builder().retry_connection(false);
+        if db_type.is_embedded() {
+            builder
+                .max_size(4)
+                .connection_timeout(Duration::from_secs(5))
+                .test_on_check_out(self.session_reset.is_enabled())
+        } else if db_type.is_cluster() {
+            builder
+                .max_size(8)
+                .connection_timeout(Duration::from_secs(30))
+                .test_on_check_out(true)
+        } else {
+            builder
+                .max_size(16)
+                .connection_timeout(Duration::from_secs(10))
+                .test_on_check_out(true)
+        }
+    }
+}
+
+/// Builds a pool for the settings with the defaults from `ConnectionManager::pool_builder`.
+/// The settings are checked and the first connection is opened here, so a misconfiguration or
+/// rejected credentials fail with their own error rather than as a timeout on the first checkout.
+pub async fn build_pool(
+    settings: DatabaseSettings,
+) -> Result<Pool<ConnectionManager>, ConnectionError> {
+    if let Some(error) = settings.invalid_setting() {
+        return Err(ConnectionError::from(error).with_context(&settings));
+    }
+    let manager = ConnectionManager::new(settings);
+    let pool = manager.pool_builder().build(manager).await?;
+    let connection = pool.dedicated_connection().await?;
+    // The pool is empty, so there is room for the connection unless it broke in the meantime.
+    let _ = pool.add(connection);
+    Ok(pool)
+}
+
+/// Returns the pool shared by the whole process, building it on first use with the settings
+/// loaded by `settings`, which may read them from anywhere, such as a secrets store. Later calls
+/// return the same pool without calling `settings`, and a failed first call leaves the pool to be
+/// built by the next one.
+///
+/// ```ignore
+/// let pool = global_pool(|| async { DatabaseSettings::from_env("SURREAL") }).await?;
+/// let connection = pool.get().await?;
+/// ```
+pub async fn global_pool<F, Fut>(
+    settings: F,
+) -> Result<&'static Pool<ConnectionManager>, ConnectionError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<DatabaseSettings, DatabaseConnectionErrors>>,
+{
+    GLOBAL_POOL
+        .get_or_try_init(|| async move { build_pool(settings().await?).await })
+        .await
+}
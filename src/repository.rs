@@ -0,0 +1,192 @@
+use crate::errors::RepositoryError;
+use crate::ConnectionManager;
+use async_trait::async_trait;
+use bb8::Pool;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use surrealdb::opt::PatchOp;
+use surrealdb::RecordIdKey;
+
+/// Typed CRUD operations on the records of one table, using connections from a pool.
+///
+/// Implementors only give the table and the pool, and every operation is provided. Records are
+/// addressed by the key of their record id, such as `"tobie"` for `person:tobie` or `7` for
+/// `person:7`. Operations on a record that does not exist return `None`. `Table` implements this
+/// for any record type.
+///
+/// ```ignore
+/// struct People(Pool<ConnectionManager>);
+///
+/// impl Repository<Person> for People {
+///     fn table(&self) -> &str {
+///         "person"
+///     }
+///
+///     fn pool(&self) -> &Pool<ConnectionManager> {
+///         &self.0
+///     }
+/// }
+///
+/// let tobie = People(pool).get_by_id("tobie").await?;
+/// ```
+#[async_trait]
+pub trait Repository<T>: Sync
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// The table the records are stored in.
+    fn table(&self) -> &str;
+
+    /// The pool connections are taken from, once for each operation.
+    fn pool(&self) -> &Pool<ConnectionManager>;
+
+    /// Returns every record in the table.
+    async fn get_all(&self) -> Result<Vec<T>, RepositoryError> {
+        let conn = self.pool().get().await?;
+        Ok(conn.select(self.table()).await?)
+    }
+
+    /// Returns the record with the given id.
+    async fn get_by_id(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+    ) -> Result<Option<T>, RepositoryError> {
+        let conn = self.pool().get().await?;
+        Ok(conn.select((self.table(), id)).await?)
+    }
+
+    /// Returns a page of records ordered by id, skipping the first `start` records.
+    async fn page(&self, start: u64, limit: u64) -> Result<Vec<T>, RepositoryError> {
+        let conn = self.pool().get().await?;
+        let mut response = conn
+            .query("SELECT * FROM type::table($table) ORDER BY id LIMIT $limit START $start")
+            .bind(("table", self.table().to_string()))
+            .bind(("limit", limit))
+            .bind(("start", start))
+            .await?;
+        Ok(response.take(0)?)
+    }
+
+    /// Counts the records in the table.
+    async fn count(&self) -> Result<u64, RepositoryError> {
+        let conn = self.pool().get().await?;
+        let mut response = conn
+            .query("SELECT count() FROM type::table($table) GROUP ALL")
+            .bind(("table", self.table().to_string()))
+            .await?;
+        let count: Option<u64> = response.take((0, "count"))?;
+        // An empty table has no group to count.
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Creates a record with a generated id, unless the record holds an id of its own, and
+    /// returns it as stored.
+    async fn create(&self, record: T) -> Result<T, RepositoryError> {
+        let conn = self.pool().get().await?;
+        let created: Option<T> = conn.create(self.table()).content(record).await?;
+        created.ok_or_else(|| RepositoryError::NotReturned {
+            table: self.table().to_string(),
+            id: None,
+        })
+    }
+
+    /// Replaces the content of the record with the given id.
+    async fn update(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+        record: T,
+    ) -> Result<Option<T>, RepositoryError> {
+        let conn = self.pool().get().await?;
+        Ok(conn.update((self.table(), id)).content(record).await?)
+    }
+
+    /// Replaces the content of the record with the given id, creating it when it does not exist.
+    async fn upsert(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+        record: T,
+    ) -> Result<T, RepositoryError> {
+        let id = id.into();
+        let conn = self.pool().get().await?;
+        let upserted: Option<T> = conn
+            .upsert((self.table(), id.clone()))
+            .content(record)
+            .await?;
+        upserted.ok_or_else(|| RepositoryError::NotReturned {
+            table: self.table().to_string(),
+            id: Some(id),
+        })
+    }
+
+    /// Sets the fields of `changes` on the record with the given id, keeping its other fields.
+    async fn merge<M>(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+        changes: M,
+    ) -> Result<Option<T>, RepositoryError>
+    where
+        M: Serialize + Send + 'static,
+    {
+        let conn = self.pool().get().await?;
+        Ok(conn.update((self.table(), id)).merge(changes).await?)
+    }
+
+    /// Applies JSON Patch operations to the record with the given id. Several operations are
+    /// given with `PatchOps`.
+    async fn patch<P>(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+        patch: P,
+    ) -> Result<Option<T>, RepositoryError>
+    where
+        P: Into<PatchOp> + Send + 'static,
+    {
+        let conn = self.pool().get().await?;
+        Ok(conn.update((self.table(), id)).patch(patch).await?)
+    }
+
+    /// Deletes the record with the given id, and returns it as it was.
+    async fn delete(
+        &self,
+        id: impl Into<RecordIdKey> + Send,
+    ) -> Result<Option<T>, RepositoryError> {
+        let conn = self.pool().get().await?;
+        Ok(conn.delete((self.table(), id)).await?)
+    }
+}
+
+/// A repository for a table given by name.
+///
+/// ```ignore
+/// let people = Table::<Person>::new(pool, "person");
+/// let count = people.count().await?;
+/// ```
+pub struct Table<T> {
+    pool: Pool<ConnectionManager>,
+    name: String,
+    record: PhantomData<fn() -> T>,
+}
+
+impl<T> Table<T> {
+    pub fn new(pool: Pool<ConnectionManager>, name: impl Into<String>) -> Self {
+        Table {
+            pool,
+            name: name.into(),
+            record: PhantomData,
+        }
+    }
+}
+
+impl<T> Repository<T> for Table<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn table(&self) -> &str {
+        &self.name
+    }
+
+    fn pool(&self) -> &Pool<ConnectionManager> {
+        &self.pool
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How `connect` retries transient failures, such as a refused socket, before giving up. Each
+/// retry waits twice as long as the one before, up to `max_delay`, and the wait is jittered so
+/// that connections failing together do not retry together. Errors that are not retryable, such
+/// as a missing namespace, are returned at once.
+///
+/// All the retries happen within one call to `connect`, so they have to fit within the pool's
+/// connection timeout to be of use.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    /// How many times a failed connection is retried. Zero disables retries.
+    pub max_retries: u32,
+    /// The wait before the first retry.
+    pub initial_delay: Duration,
+    /// The longest wait between two retries.
+    pub max_delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns failures at once without retrying.
+    pub fn none() -> Self {
+        Backoff {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// The wait before the given retry, counting from zero. Half of the wait is fixed and the
+    /// other half is random, so it is between half and all of `initial_delay * 2^retry`, capped
+    /// at `max_delay`.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// A random fraction between zero and one. The standard library seeds every `RandomState`
+/// randomly, which is random enough to spread retries out.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
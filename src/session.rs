@@ -0,0 +1,124 @@
+use crate::connection::SurrealConnection;
+use crate::errors::DatabaseConnectionErrors;
+use crate::hook::OnConnect;
+use crate::DatabaseSettings;
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use crate::DatabaseType;
+use std::collections::BTreeSet;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+
+/// What is restored on a connection before the pool hands it to the next caller, so that it does
+/// not inherit the session the previous caller left behind.
+///
+/// The reset runs on checkout, as part of `is_valid`, so it only runs when the pool is built with
+/// `test_on_check_out`. `ConnectionManager::pool_builder` turns that on whenever a reset is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionReset {
+    /// Selects the namespace and database from the settings again.
+    pub namespace: bool,
+    /// Unsets the parameters set with `SurrealConnection::set`, and runs the `on_connect` hook
+    /// again so that the parameters it sets are restored.
+    pub parameters: bool,
+    /// Signs in again with the authentication from the settings, when the previous caller
+    /// signed in, signed up, authenticated or invalidated through the connection.
+    pub authentication: bool,
+}
+
+impl Default for SessionReset {
+    /// Restores the namespace, database, parameters and authentication.
+    fn default() -> Self {
+        SessionReset {
+            namespace: true,
+            parameters: true,
+            authentication: true,
+        }
+    }
+}
+
+impl SessionReset {
+    /// Leaves the session as the previous caller left it.
+    pub fn none() -> Self {
+        SessionReset {
+            namespace: false,
+            parameters: false,
+            authentication: false,
+        }
+    }
+
+    /// Whether anything is restored.
+    pub fn is_enabled(&self) -> bool {
+        self.namespace || self.parameters || self.authentication
+    }
+
+    /// Restores the session of a connection that is being checked out.
+    pub(crate) async fn run(
+        &self,
+        conn: &SurrealConnection,
+        settings: &DatabaseSettings,
+        on_connect: Option<&OnConnect>,
+    ) -> Result<(), DatabaseConnectionErrors> {
+        let changes = conn.take_session_changes();
+        // The client itself is used, as the methods of the connection would record the changes.
+        let client: &Surreal<Any> = conn;
+        if self.authentication && changes.authentication {
+            client
+                .invalidate()
+                .await
+                .map_err(|e| DatabaseConnectionErrors::AuthenticationFailed(Box::new(e)))?;
+            #[cfg(any(feature = "kv-websocket", feature = "http"))]
+            if is_remote(&settings.db_type) {
+                settings
+                    .auth
+                    .apply(
+                        client,
+                        settings.namespace.as_str(),
+                        settings.database_name.as_str(),
+                    )
+                    .await?;
+            }
+        }
+        if self.namespace {
+            client
+                .use_ns(settings.namespace.as_str())
+                .use_db(settings.database_name.as_str())
+                .await
+                .map_err(|e| DatabaseConnectionErrors::NamespaceSelectionFailed(Box::new(e)))?;
+        }
+        if self.parameters && !changes.parameters.is_empty() {
+            for key in changes.parameters {
+                client
+                    .unset(key)
+                    .await
+                    .map_err(|e| DatabaseConnectionErrors::SessionResetFailed(Box::new(e)))?;
+            }
+            // The previous caller may have overwritten a parameter set by the hook.
+            if let Some(hook) = on_connect {
+                hook(client, settings)
+                    .await
+                    .map_err(DatabaseConnectionErrors::OnConnectFailed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The changes made to the session of a connection through its methods since it was last reset.
+#[derive(Debug, Default)]
+pub(crate) struct SessionChanges {
+    pub(crate) parameters: BTreeSet<String>,
+    pub(crate) authentication: bool,
+}
+
+/// Whether connections to this database type sign in with the authentication from the settings.
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+fn is_remote(db_type: &DatabaseType) -> bool {
+    match *db_type {
+        #[cfg(feature = "kv-websocket")]
+        DatabaseType::WebSocket | DatabaseType::WebSocketSecure => true,
+        #[cfg(feature = "http")]
+        DatabaseType::Http | DatabaseType::Https => true,
+        #[allow(unreachable_patterns)]
+        _ => false,
+    }
+}
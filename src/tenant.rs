@@ -0,0 +1,380 @@
+use crate::connection::SurrealConnection;
+use crate::errors::ConnectionError;
+use crate::{ConnectionManager, DatabaseSettings};
+use bb8::{Pool, PooledConnection, RunError};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::time;
+
+type ConfigureManager = dyn Fn(ConnectionManager) -> ConnectionManager + Send + Sync;
+
+/// A namespace and database pair that a tenant's connections use.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tenant {
+    pub namespace: String,
+    pub database_name: String,
+}
+
+struct TenantEntry {
+    pool: Pool<ConnectionManager>,
+    last_used: Instant,
+    checked_out: u32,
+}
+
+#[derive(Default)]
+struct Tenants {
+    entries: HashMap<Tenant, TenantEntry>,
+    /// Connections being opened for checkouts, which their pools do not count yet.
+    opening: u32,
+}
+
+/// The state shared by a `TenantPool` and the connections checked out from it.
+#[derive(Default)]
+struct Shared {
+    tenants: Mutex<Tenants>,
+    /// Notified whenever a checkout ends, for the checkouts waiting on a limit.
+    released: Notify,
+}
+
+/// Pools connections for many tenants, each in its own namespace and database.
+///
+/// A pool is created for a tenant the first time it is used, from the shared settings with the
+/// tenant's namespace and database. The number of connections open at once is limited across all
+/// tenants: when a checkout needs a new connection at the limit, the least recently used tenant
+/// with no connection checked out is evicted to make room, or the checkout waits for one. Idle
+/// connections are closed after the idle timeout, and tenants that have not been used for the
+/// idle timeout are evicted along with their pool.
+///
+/// Embedded datastores are opened once for all tenants, and the pool of each tenant connects to
+/// it with sessions of its own, so the tenants do not wait on each other.
+///
+/// ```ignore
+/// let tenants = TenantPool::new(settings).with_max_connections(32);
+/// let connection = tenants.get("acme", "main").await?;
+/// ```
+pub struct TenantPool {
+    settings: DatabaseSettings,
+    max_connections: u32,
+    max_connections_per_tenant: u32,
+    connection_timeout: Duration,
+    idle_timeout: Duration,
+    configure: Option<Arc<ConfigureManager>>,
+    /// The manager that the managers of the tenants are made from, sharing its embedded
+    /// datastore.
+    template: ConnectionManager,
+    shared: Arc<Shared>,
+}
+
+/// How many connections may be open at once across all tenants.
+const DEFAULT_MAX_CONNECTIONS: u32 = 64;
+
+/// How many connections each tenant may have checked out at once.
+const DEFAULT_MAX_CONNECTIONS_PER_TENANT: u32 = 8;
+
+/// How long a checkout waits for a connection.
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a connection or tenant may stay unused before it is closed.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often a waiting checkout checks the limits again, as the pools close idle and broken
+/// connections without notice.
+const RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+impl TenantPool {
+    /// Creates a tenant pool from settings whose namespace and database are replaced by those
+    /// of each tenant.
+    pub fn new(settings: DatabaseSettings) -> Self {
+        TenantPool {
+            template: ConnectionManager::new(settings.clone()),
+            settings,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_tenant: DEFAULT_MAX_CONNECTIONS_PER_TENANT,
+            connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            configure: None,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Sets how many connections may be open at once across all tenants, whether checked out
+    /// or idle.
+    pub fn with_max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Sets how many connections each tenant may have checked out at once.
+    pub fn with_max_connections_per_tenant(mut self, max_connections: u32) -> Self {
+        self.max_connections_per_tenant = max_connections;
+        self
+    }
+
+    /// Sets how long a checkout waits for a connection, including the time spent waiting on
+    /// the limits.
+    pub fn with_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.connection_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a connection or tenant may stay unused before it is closed.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Configures the connection manager of each tenant, such as its health check or
+    /// `on_connect` hook.
+    pub fn with_manager<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(ConnectionManager) -> ConnectionManager + Send + Sync + 'static,
+    {
+        self.configure = Some(Arc::new(configure));
+        self
+    }
+
+    /// Checks out a connection for the tenant, creating the tenant's pool on first use.
+    pub async fn get(
+        &self,
+        namespace: &str,
+        database_name: &str,
+    ) -> Result<TenantConnection, RunError<ConnectionError>> {
+        let tenant = Tenant {
+            namespace: namespace.to_string(),
+            database_name: database_name.to_string(),
+        };
+        time::timeout(self.connection_timeout, self.checkout(tenant))
+            .await
+            .map_err(|_| RunError::TimedOut)?
+    }
+
+    /// The tenants that currently have a pool.
+    pub fn tenants(&self) -> Vec<Tenant> {
+        self.shared.lock().entries.keys().cloned().collect()
+    }
+
+    /// Evicts the tenants that have not been used for the idle timeout and have no connection
+    /// checked out, and returns how many were evicted. This also happens on every checkout, so
+    /// it only needs to be called to free idle tenants when there are no checkouts.
+    pub fn evict_idle(&self) -> usize {
+        self.shared.lock().evict_idle(self.idle_timeout)
+    }
+
+    async fn checkout(
+        &self,
+        tenant: Tenant,
+    ) -> Result<TenantConnection, RunError<ConnectionError>> {
+        let (pool, mut checkout) = loop {
+            let released = self.shared.released.notified();
+            tokio::pin!(released);
+            // Registers for the notification before the limits are checked, so none is missed.
+            released.as_mut().enable();
+            if let Some(reserved) = self.reserve(&tenant)? {
+                break reserved;
+            }
+            tokio::select! {
+                _ = released => {}
+                _ = time::sleep(RECHECK_INTERVAL) => {}
+            }
+        };
+        let conn = pool.get_owned().await?;
+        checkout.opened();
+        Ok(TenantConnection {
+            conn,
+            _checkout: checkout,
+        })
+    }
+
+    /// Counts a checkout for the tenant against the limits and returns the pool to check out
+    /// from, or `None` when the checkout has to wait.
+    fn reserve(
+        &self,
+        tenant: &Tenant,
+    ) -> Result<Option<(Pool<ConnectionManager>, Checkout)>, RunError<ConnectionError>> {
+        let mut tenants = self.shared.lock();
+        tenants.evict_idle(self.idle_timeout);
+        if !tenants.entries.contains_key(tenant) {
+            let settings = self.tenant_settings(tenant);
+            // An invalid tenant is reported rather than cached.
+            if let Some(error) = settings.invalid_setting() {
+                return Err(RunError::User(
+                    ConnectionError::from(error).with_context(&settings),
+                ));
+            }
+            let pool = self.build_pool(settings);
+            tenants.entries.insert(
+                tenant.clone(),
+                TenantEntry {
+                    pool,
+                    last_used: Instant::now(),
+                    checked_out: 0,
+                },
+            );
+        }
+        let entry = &tenants.entries[tenant];
+        if entry.checked_out >= self.max_connections_per_tenant {
+            return Ok(None);
+        }
+        let pool = entry.pool.clone();
+        // Every open connection of the tenant is taken, so the pool opens a new one.
+        let opening = entry.checked_out >= pool.state().connections;
+        if opening {
+            if !tenants.make_room(tenant, self.max_connections) {
+                return Ok(None);
+            }
+            tenants.opening += 1;
+        }
+        let entry = tenants
+            .entries
+            .get_mut(tenant)
+            .expect("the entry was checked above");
+        entry.checked_out += 1;
+        entry.last_used = Instant::now();
+        // Released when dropped, which takes the lock, so it is only created once the checkout
+        // is counted.
+        let checkout = Checkout {
+            shared: self.shared.clone(),
+            tenant: tenant.clone(),
+            opening,
+        };
+        Ok(Some((pool, checkout)))
+    }
+
+    /// The settings of the shared template with the tenant's namespace and database.
+    fn tenant_settings(&self, tenant: &Tenant) -> DatabaseSettings {
+        DatabaseSettings {
+            namespace: tenant.namespace.clone(),
+            database_name: tenant.database_name.clone(),
+            ..self.settings.clone()
+        }
+    }
+
+    fn manager(&self, settings: DatabaseSettings) -> ConnectionManager {
+        let manager = self.template.for_settings(settings);
+        match &self.configure {
+            Some(configure) => configure(manager),
+            None => manager,
+        }
+    }
+
+    /// Builds the pool of a tenant.
+    fn build_pool(&self, settings: DatabaseSettings) -> Pool<ConnectionManager> {
+        let manager = self.manager(settings);
+        manager
+            .pool_builder()
+            .max_size(self.max_connections_per_tenant)
+            .min_idle(None)
+            .connection_timeout(self.connection_timeout)
+            .idle_timeout(Some(self.idle_timeout))
+            .build_unchecked(manager)
+    }
+
+}
+
+impl Tenants {
+    fn evict_idle(&mut self, idle_timeout: Duration) -> usize {
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| entry.checked_out > 0 || entry.last_used.elapsed() < idle_timeout);
+        before - self.entries.len()
+    }
+
+    /// Evicts the least recently used tenants with no connection checked out until a connection
+    /// can be opened for the tenant within the limit, and returns whether it can.
+    fn make_room(&mut self, tenant: &Tenant, max_connections: u32) -> bool {
+        loop {
+            let open: u32 = self
+                .entries
+                .values()
+                .map(|entry| entry.pool.state().connections)
+                .sum();
+            if open + self.opening < max_connections {
+                return true;
+            }
+            let evicted = self
+                .entries
+                .iter()
+                .filter(|(other, entry)| {
+                    *other != tenant
+                        && entry.checked_out == 0
+                        && entry.pool.state().connections > 0
+                })
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(other, _)| other.clone());
+            match evicted {
+                // Dropping the pool closes its idle connections.
+                Some(other) => {
+                    self.entries.remove(&other);
+                }
+                None => return false,
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Tenants> {
+        // The state is left consistent by every operation, so a panic elsewhere cannot poison it.
+        self.tenants
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A checkout counted against the limits of a `TenantPool`, which is released when dropped.
+struct Checkout {
+    shared: Arc<Shared>,
+    tenant: Tenant,
+    /// Whether the checkout is counted as opening a connection that its pool does not count yet.
+    opening: bool,
+}
+
+impl Checkout {
+    /// Stops counting the connection as being opened, as the pool now counts it.
+    fn opened(&mut self) {
+        if self.opening {
+            self.shared.lock().opening -= 1;
+            self.opening = false;
+        }
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let mut tenants = self.shared.lock();
+        if self.opening {
+            tenants.opening -= 1;
+        }
+        if let Some(entry) = tenants.entries.get_mut(&self.tenant) {
+            entry.checked_out -= 1;
+            entry.last_used = Instant::now();
+        }
+        drop(tenants);
+        self.shared.released.notify_waiters();
+    }
+}
+
+/// A connection checked out from a `TenantPool`. It dereferences to the connection, and returns
+/// it to the tenant's pool when dropped.
+pub struct TenantConnection {
+    // Dropped first, so that the connection is back in its pool when the checkout is released.
+    conn: PooledConnection<'static, ConnectionManager>,
+    _checkout: Checkout,
+}
+
+impl Deref for TenantConnection {
+    type Target = SurrealConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl DerefMut for TenantConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use std::{error::Error, sync::Arc};
+
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+type TlsError = Box<dyn Error + Send + Sync>;
+
+/// TLS options for the secure remote database types (`wss://` and `https://`).
+/// When no options are given, the server certificate is verified against the bundled web PKI roots.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// Path to a PEM file of CA certificates to trust instead of the web PKI roots.
+    pub ca_bundle: Option<String>,
+    /// Path to a PEM file with the certificate chain presented to the server.
+    pub client_certificate: Option<String>,
+    /// Path to a PEM file with the private key for the client certificate.
+    pub client_key: Option<String>,
+    /// Accepts any server certificate without verification.
+    /// This removes the protection TLS gives against impersonation, so only use it for development.
+    pub danger_accept_invalid_certificates: bool,
+}
+
+impl TlsSettings {
+    /// Builds the rustls client configuration described by these settings.
+    #[cfg(any(feature = "kv-websocket", feature = "http"))]
+    pub(crate) fn client_config(&self) -> Result<ClientConfig, TlsError> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+        let builder = if self.danger_accept_invalid_certificates {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoCertificateVerification(provider)))
+        } else {
+            builder.with_root_certificates(self.root_certificates()?)
+        };
+        match (&self.client_certificate, &self.client_key) {
+            (Some(certificate), Some(key)) => {
+                let chain =
+                    CertificateDer::pem_file_iter(certificate)?.collect::<Result<Vec<_>, _>>()?;
+                let key = PrivateKeyDer::from_pem_file(key)?;
+                Ok(builder.with_client_auth_cert(chain, key)?)
+            }
+            (None, None) => Ok(builder.with_no_client_auth()),
+            _ => Err("a client certificate and client key must be provided together".into()),
+        }
+    }
+
+    /// Loads the trusted CA certificates from the CA bundle or the web PKI roots.
+    #[cfg(any(feature = "kv-websocket", feature = "http"))]
+    fn root_certificates(&self) -> Result<RootCertStore, TlsError> {
+        let Some(ca_bundle) = &self.ca_bundle else {
+            return Ok(RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            });
+        };
+        let mut roots = RootCertStore::empty();
+        for certificate in CertificateDer::pem_file_iter(ca_bundle)? {
+            roots.add(certificate?)?;
+        }
+        if roots.is_empty() {
+            return Err("the CA bundle does not contain any certificates".into());
+        }
+        Ok(roots)
+    }
+}
+
+/// Accepts every server certificate while still checking handshake signatures.
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+#[derive(Debug)]
+struct NoCertificateVerification(Arc<CryptoProvider>);
+
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,167 @@
+use crate::connection::SurrealConnection;
+use crate::errors::TransactionError;
+use crate::retry::Backoff;
+use crate::ConnectionManager;
+use async_trait::async_trait;
+use bb8::Pool;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use surrealdb::engine::any::Any;
+use surrealdb::err::Error as DbError;
+use surrealdb::method::Query;
+use surrealdb::Response;
+
+type Binding = Box<dyn for<'r> FnOnce(Query<'r, Any>) -> Query<'r, Any> + Send>;
+
+/// The statements of a transaction, collected by the closure given to
+/// `PoolTransaction::transaction` and sent together as one query.
+///
+/// Clones share the same statements, so the transaction can be moved into an `async` block.
+#[derive(Clone, Default)]
+pub struct Transaction {
+    inner: Arc<Mutex<Statements>>,
+}
+
+#[derive(Default)]
+struct Statements {
+    statements: Vec<String>,
+    bindings: Vec<Binding>,
+}
+
+impl Transaction {
+    /// Adds one or more SurrealQL statements to the transaction. They must not begin, commit or
+    /// cancel a transaction themselves.
+    pub fn query(&self, statements: impl Into<String>) -> &Self {
+        self.lock().statements.push(statements.into());
+        self
+    }
+
+    /// Binds a parameter for every statement in the transaction.
+    pub fn bind(&self, key: impl Into<String>, value: impl Serialize + Send + 'static) -> &Self {
+        let key = key.into();
+        self.lock()
+            .bindings
+            .push(Box::new(move |query| query.bind((key, value))));
+        self
+    }
+
+    /// Sends the statements on the connection in one transaction.
+    async fn send(self, conn: &SurrealConnection) -> surrealdb::Result<Response> {
+        let Statements {
+            statements,
+            bindings,
+        } = std::mem::take(&mut *self.lock());
+        let mut query = String::from("BEGIN TRANSACTION;\n");
+        for statement in statements {
+            query.push_str(&statement);
+            query.push_str(";\n");
+        }
+        query.push_str("COMMIT TRANSACTION;");
+        let query = bindings
+            .into_iter()
+            .fold(conn.query(query), |query, bind| bind(query));
+        conn.observe(query.await)?.check()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Statements> {
+        // The statements are left consistent by every operation, so a panic elsewhere cannot
+        // poison them.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Runs transactions on connections from the pool.
+#[async_trait]
+pub trait PoolTransaction {
+    /// Runs a transaction, retrying write conflicts as set by the default `Backoff`.
+    /// See `transaction_with`.
+    async fn transaction<F, Fut, T, E>(
+        &self,
+        build: F,
+    ) -> Result<(T, Response), TransactionError<E>>
+    where
+        F: Fn(Transaction) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, E>> + Send,
+        T: Send,
+        E: Send,
+    {
+        self.transaction_with(&Backoff::default(), build).await
+    }
+
+    /// Runs a transaction whose statements are added by `build`, and returns the value returned
+    /// by `build` with the response to the statements, which holds their results in order.
+    ///
+    /// The statements are sent as a single query between `BEGIN` and `COMMIT`, so a connection is
+    /// never left inside an open transaction. When `build` returns an error or panics, nothing
+    /// is sent. When a statement fails, the whole transaction is rolled back. A transaction that
+    /// fails with a read or write conflict is built and sent again, waiting between attempts as
+    /// set by `backoff`.
+    ///
+    /// ```ignore
+    /// let (_, mut response) = pool
+    ///     .transaction(|tx| async move {
+    ///         tx.query("UPDATE account:from SET balance -= $amount")
+    ///             .query("UPDATE account:to SET balance += $amount")
+    ///             .bind("amount", 10);
+    ///         Ok::<_, Infallible>(())
+    ///     })
+    ///     .await?;
+    /// ```
+    async fn transaction_with<F, Fut, T, E>(
+        &self,
+        backoff: &Backoff,
+        build: F,
+    ) -> Result<(T, Response), TransactionError<E>>
+    where
+        F: Fn(Transaction) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, E>> + Send,
+        T: Send,
+        E: Send;
+}
+
+#[async_trait]
+impl PoolTransaction for Pool<ConnectionManager> {
+    async fn transaction_with<F, Fut, T, E>(
+        &self,
+        backoff: &Backoff,
+        build: F,
+    ) -> Result<(T, Response), TransactionError<E>>
+    where
+        F: Fn(Transaction) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<T, E>> + Send,
+        T: Send,
+        E: Send,
+    {
+        let mut retry = 0;
+        loop {
+            // The connection is only checked out once the statements are built, so it is not
+            // held while the closure runs.
+            let tx = Transaction::default();
+            let value = build(tx.clone()).await.map_err(TransactionError::Aborted)?;
+            let conn = self.get().await.map_err(TransactionError::Pool)?;
+            match tx.send(&conn).await {
+                Ok(response) => return Ok((value, response)),
+                Err(error) if is_conflict(&error) && retry < backoff.max_retries => {
+                    drop(conn);
+                    tokio::time::sleep(backoff.delay(retry)).await;
+                    retry += 1;
+                }
+                Err(error) => return Err(TransactionError::Failed(error)),
+            }
+        }
+    }
+}
+
+/// Whether a transaction failed because it conflicted with another one, so that running it
+/// again may succeed. Remote engines only pass on the message of the error.
+fn is_conflict(error: &surrealdb::Error) -> bool {
+    match error {
+        surrealdb::Error::Db(DbError::TxRetryable) => true,
+        error => error
+            .to_string()
+            .contains("This transaction can be retried"),
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "kv-websocket")]
+
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::DatabaseSettings;
+
+fn websocket(host: &str, port: u16) -> Result<DatabaseSettings, DatabaseConnectionErrors> {
+    DatabaseSettings::builder()
+        .websocket(host, port)
+        .namespace("app")
+        .database("main")
+        .build()
+}
+
+#[test]
+fn test_valid_hosts_and_ports() {
+    for host in [
+        "localhost",
+        "db.example.com",
+        "db.example.com.",
+        "surreal_db",
+        "10.0.0.1",
+        "::1",
+        "[fe80::1]",
+    ] {
+        for port in [80, 443, 1024, 8000] {
+            if let Err(error) = websocket(host, port) {
+                panic!("Expected {}:{} to be valid, got {:?}", host, port, error);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_invalid_hosts_are_reported() {
+    for host in [
+        "",
+        "db example.com",
+        "-db.example.com",
+        "db..example.com",
+        "256.0.0.1",
+        "[::1",
+        "[10.0.0.1]",
+        "ws://localhost",
+    ] {
+        match websocket(host, 8000) {
+            Err(DatabaseConnectionErrors::InvalidHost(invalid)) => assert_eq!(invalid, host),
+            other => panic!("Expected {:?} to be rejected, got {:?}", host, other),
+        }
+    }
+    let error = websocket("db example.com", 8000).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "DatabaseConnectionError: Invalid host error: `db example.com`"
+    );
+}
+
+#[test]
+fn test_default_ports() {
+    let settings = websocket("localhost", 0).unwrap();
+    assert_eq!(settings.to_url(), "ws://localhost:80/app/main");
+
+    let settings = DatabaseSettings::builder()
+        .websocket_secure("db.example.com", 0)
+        .namespace("app")
+        .database("main")
+        .build()
+        .unwrap();
+    assert_eq!(settings.to_url(), "wss://db.example.com:443/app/main");
+}
+
+#[test]
+fn test_ipv6_addresses() {
+    let settings = websocket("::1", 8000).unwrap();
+    assert_eq!(settings.to_url(), "ws://[::1]:8000/app/main");
+
+    let parsed = DatabaseSettings::from_url("ws://[::1]:8000/app/main").unwrap();
+    assert_eq!(parsed.host, "::1");
+    assert_eq!(parsed.port, 8000);
+}
@@ -0,0 +1,217 @@
+#![cfg(feature = "kv-websocket")]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::{ManageConnection, Pool};
+use bb8_surrealdb2::auth::Authentication;
+use bb8_surrealdb2::errors::ConnectionError;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+use common::{remote_settings, StandIn};
+use std::collections::BTreeMap;
+use surrealdb::engine::any;
+use surrealdb::opt::auth::Record;
+
+const RECORD_ACCESS: &str = "
+    USE NS test DB test;
+    DEFINE ACCESS account ON DATABASE TYPE RECORD
+        SIGNIN (SELECT * FROM user WHERE email = $email AND crypto::argon2::compare(pass, $pass));
+    CREATE user:alice SET email = 'alice@example.com', pass = crypto::argon2::generate('secret');
+";
+
+fn settings(port: u16, auth: Authentication) -> DatabaseSettings {
+    DatabaseSettings {
+        auth,
+        ..remote_settings(DatabaseType::WebSocket, port)
+    }
+}
+
+fn alice() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("email".to_string(), "alice@example.com".to_string()),
+        ("pass".to_string(), "secret".to_string()),
+    ])
+}
+
+/// Checks that a pooled connection can write and read back a record as the signed in user.
+async fn assert_read_write(manager: ConnectionManager) {
+    let pool = Pool::builder().max_size(2).build(manager).await.unwrap();
+    let connection = pool.get().await.unwrap();
+    let mut response = connection
+        .query("CREATE note:one SET text = 'hello'; RETURN (SELECT VALUE text FROM note:one)")
+        .await
+        .unwrap();
+    let text: Vec<String> = response.take(1).unwrap();
+    assert_eq!(text, vec!["hello".to_string()]);
+}
+
+async fn assert_connect_error(manager: ConnectionManager, expected: &str) {
+    match manager.connect().await {
+        Err(ConnectionError { error, .. }) => assert_eq!(error.as_str(), expected),
+        Ok(_) => panic!("Expected the connection to fail"),
+    }
+}
+
+/// The record the connection is signed in as.
+async fn signed_in_record(connection: &surrealdb::Surreal<any::Any>) -> Option<String> {
+    let mut response = connection.query("RETURN <string> $auth").await.unwrap();
+    response.take(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_namespace_user() {
+    let server = StandIn::ws().await;
+    server
+        .execute("USE NS test; DEFINE USER editor ON NAMESPACE PASSWORD 'secret' ROLES EDITOR;")
+        .await;
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Namespace {
+            username: "editor".to_string(),
+            password: "secret".to_string(),
+        },
+    ));
+    assert_read_write(manager).await;
+}
+
+#[tokio::test]
+async fn test_database_user() {
+    let server = StandIn::ws().await;
+    server
+        .execute(
+            "USE NS test DB test; DEFINE USER editor ON DATABASE PASSWORD 'secret' ROLES EDITOR;",
+        )
+        .await;
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Database {
+            username: "editor".to_string(),
+            password: "secret".to_string(),
+        },
+    ));
+    assert_read_write(manager).await;
+
+    // The database user does not exist on the namespace, so it cannot sign in there.
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Namespace {
+            username: "editor".to_string(),
+            password: "secret".to_string(),
+        },
+    ));
+    assert_connect_error(manager, "Authentication error").await;
+}
+
+#[tokio::test]
+async fn test_record_access() {
+    let server = StandIn::ws().await;
+    server.execute(RECORD_ACCESS).await;
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Record {
+            access: "account".to_string(),
+            params: alice(),
+        },
+    ));
+    let connection = manager.connect().await.unwrap();
+    assert_eq!(
+        signed_in_record(&connection).await,
+        Some("user:alice".to_string())
+    );
+
+    let mut params = alice();
+    params.insert("pass".to_string(), "wrong".to_string());
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Record {
+            access: "account".to_string(),
+            params,
+        },
+    ));
+    assert_connect_error(manager, "Authentication error").await;
+}
+
+#[tokio::test]
+async fn test_token() {
+    let server = StandIn::ws().await;
+    server.execute(RECORD_ACCESS).await;
+    let client = any::connect(format!("ws://127.0.0.1:{}", server.port()))
+        .await
+        .unwrap();
+    let token = client
+        .signin(Record {
+            namespace: "test",
+            database: "test",
+            access: "account",
+            params: alice(),
+        })
+        .await
+        .unwrap()
+        .into_insecure_token();
+
+    let manager = ConnectionManager::new(settings(server.port(), Authentication::Token(token)));
+    let connection = manager.connect().await.unwrap();
+    assert_eq!(
+        signed_in_record(&connection).await,
+        Some("user:alice".to_string())
+    );
+
+    let manager = ConnectionManager::new(settings(
+        server.port(),
+        Authentication::Token("not-a-token".to_string()),
+    ));
+    assert_connect_error(manager, "Authentication error").await;
+}
+
+#[tokio::test]
+async fn test_no_authentication() {
+    let server = StandIn::ws().await;
+    let manager = ConnectionManager::new(settings(server.port(), Authentication::None));
+    let connection = manager.connect().await.unwrap();
+    let mut response = connection.query("RETURN session::ns()").await.unwrap();
+    let namespace: Option<String> = response.take(0).unwrap();
+    assert_eq!(namespace, Some("test".to_string()));
+}
+
+#[tokio::test]
+async fn test_missing_credentials() {
+    let missing = [
+        (Authentication::root("", "secret"), "Username not set error"),
+        (
+            Authentication::Database {
+                username: "editor".to_string(),
+                password: "".to_string(),
+            },
+            "Password not set error",
+        ),
+        (
+            Authentication::Record {
+                access: "".to_string(),
+                params: alice(),
+            },
+            "Access method not set error",
+        ),
+        (Authentication::Token("".to_string()), "Token not set error"),
+    ];
+    for (auth, expected) in missing {
+        // The settings are rejected before connecting, so nothing needs to listen on the port.
+        assert_connect_error(ConnectionManager::new(settings(8000, auth)), expected).await;
+    }
+}
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let auth = [
+        Authentication::root("root", "hunter2"),
+        Authentication::Record {
+            access: "account".to_string(),
+            params: BTreeMap::from([("pass".to_string(), "hunter2".to_string())]),
+        },
+        Authentication::Token("hunter2".to_string()),
+    ];
+    for auth in auth {
+        let debug = format!("{:?}", settings(8000, auth));
+        assert!(!debug.contains("hunter2"), "{}", debug);
+    }
+}
@@ -0,0 +1,112 @@
+extern crate bb8;
+extern crate tokio;
+
+use bb8::Pool;
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+
+#[tokio::test]
+async fn test_memory_builder() {
+    let settings = DatabaseSettings::builder()
+        .memory()
+        .namespace("builder")
+        .database("test")
+        .build()
+        .unwrap();
+    assert!(matches!(settings.db_type, DatabaseType::Memory));
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(ConnectionManager::new(settings))
+        .await
+        .unwrap();
+    let connection = pool.get().await.unwrap();
+    connection.health().await.unwrap();
+}
+
+#[test]
+fn test_empty_namespace_fails_at_build() {
+    let error = DatabaseSettings::builder()
+        .database("test")
+        .memory()
+        .namespace("")
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, DatabaseConnectionErrors::InvalidNamespace));
+}
+
+#[cfg(feature = "kv-surreal")]
+#[test]
+fn test_empty_path_fails_at_build() {
+    let error = DatabaseSettings::builder()
+        .surrealkv("")
+        .namespace("test")
+        .database("test")
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, DatabaseConnectionErrors::InvalidPath));
+}
+
+#[cfg(feature = "kv-websocket")]
+mod remote {
+    use super::*;
+    use bb8_surrealdb2::auth::Authentication;
+    use bb8_surrealdb2::tls::TlsSettings;
+
+    #[test]
+    fn test_websocket_builder() {
+        let settings = DatabaseSettings::builder()
+            .websocket("localhost", 8000)
+            .namespace("app")
+            .database("main")
+            .root_auth("root", "secret")
+            .build()
+            .unwrap();
+        assert!(matches!(settings.db_type, DatabaseType::WebSocket));
+        assert_eq!(settings.host, "localhost");
+        assert_eq!(settings.port, 8000);
+        assert_eq!(settings.namespace, "app");
+        assert_eq!(settings.database_name, "main");
+        assert!(matches!(
+            &settings.auth,
+            Authentication::Root { username, password } if username == "root" && password == "secret"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_remote_settings_fail_at_build() {
+        let error = DatabaseSettings::builder()
+            .websocket("", 8000)
+            .namespace("app")
+            .database("main")
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, DatabaseConnectionErrors::InvalidHost(_)));
+
+        let error = DatabaseSettings::builder()
+            .websocket("localhost", 8000)
+            .namespace("app")
+            .database("main")
+            .root_auth("root", "")
+            .build()
+            .unwrap_err();
+        assert!(matches!(error, DatabaseConnectionErrors::InvalidPassword));
+    }
+
+    #[test]
+    fn test_unreadable_tls_files_fail_at_build() {
+        let error = DatabaseSettings::builder()
+            .websocket_secure("localhost", 8000)
+            .namespace("app")
+            .database("main")
+            .tls(TlsSettings {
+                ca_bundle: Some("/nonexistent/ca.pem".to_string()),
+                ..Default::default()
+            })
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            DatabaseConnectionErrors::InvalidTlsConfiguration(_)
+        ));
+    }
+}
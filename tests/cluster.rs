@@ -0,0 +1,154 @@
+#![cfg(any(feature = "kv-tikv", feature = "kv-fdb"))]
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::ManageConnection;
+use bb8_surrealdb2::auth::Authentication;
+use bb8_surrealdb2::cluster::ClusterSettings;
+use bb8_surrealdb2::errors::{DatabaseConnectionErrors, ErrorKind};
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+use common::EndpointStandIn;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+fn settings(db_type: DatabaseType, path: String, cluster: ClusterSettings) -> DatabaseSettings {
+    DatabaseSettings {
+        db_type,
+        auth: Authentication::None,
+        port: 0,
+        host: "".to_string(),
+        path,
+        namespace: "test".to_string(),
+        database_name: "test".to_string(),
+        tls: None,
+        cluster: Some(cluster),
+    }
+}
+
+/// Starts connecting in the background and waits for the stand-in to accept `connections`
+/// connections. The stand-in never answers, so the connection itself is not expected to complete.
+async fn assert_contacted(
+    manager: ConnectionManager,
+    endpoint: &EndpointStandIn,
+    connections: usize,
+) {
+    let connecting = tokio::spawn(async move { manager.connect().await.map(|_| ()) });
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while endpoint.accepted() < connections {
+        assert!(
+            Instant::now() < deadline,
+            "The stand-in endpoint was not contacted"
+        );
+        sleep(Duration::from_millis(50)).await;
+    }
+    connecting.abort();
+}
+
+#[cfg(feature = "kv-tikv")]
+mod tikv {
+    use super::*;
+    use common::closed_addr;
+    use tokio::time::timeout;
+
+    fn manager(pd_endpoints: Vec<String>) -> ConnectionManager {
+        ConnectionManager::new(settings(
+            DatabaseType::TiKV,
+            "".to_string(),
+            ClusterSettings {
+                pd_endpoints,
+                transaction_timeout: Some(Duration::from_secs(5)),
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_pd_endpoints() {
+        let endpoints = vec![
+            closed_addr().await.to_string(),
+            closed_addr().await.to_string(),
+        ];
+        let result = timeout(
+            Duration::from_secs(10),
+            manager(endpoints.clone()).connect(),
+        )
+        .await
+        .unwrap();
+        let error = match result {
+            Err(error) => error,
+            Ok(_) => panic!("Expected unreachable endpoints"),
+        };
+        assert_eq!(error.kind(), ErrorKind::Network);
+        assert!(error.is_retryable());
+        let context = error.context.as_ref().unwrap();
+        assert_eq!(context.engine, "tikv");
+        assert_eq!(context.address, endpoints.join(","));
+        match error.error {
+            DatabaseConnectionErrors::UnreachableEndpoints(tried) => assert_eq!(tried, endpoints),
+            other => panic!("Expected unreachable endpoints, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pd_endpoints_are_tried_in_order() {
+        let unused = EndpointStandIn::start().await;
+        let endpoint = EndpointStandIn::start().await;
+        let manager = manager(vec![
+            closed_addr().await.to_string(),
+            endpoint.addr.to_string(),
+            unused.addr.to_string(),
+        ]);
+        assert_contacted(manager, &endpoint, 1).await;
+        assert_eq!(unused.accepted(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pd_endpoint_from_host_and_port() {
+        let endpoint = EndpointStandIn::start().await;
+        let settings =
+            DatabaseSettings::from_url(&format!("tikv://{}/test/test", endpoint.addr)).unwrap();
+        assert!(settings.cluster.is_none());
+        assert_contacted(ConnectionManager::new(settings), &endpoint, 1).await;
+    }
+}
+
+#[cfg(feature = "kv-fdb")]
+mod fdb {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_missing_cluster_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fdb.cluster");
+        let manager = ConnectionManager::new(settings(
+            DatabaseType::FoundationDB,
+            path.to_str().unwrap().to_string(),
+            ClusterSettings::default(),
+        ));
+        match manager.connect().await {
+            Err(error) => assert!(matches!(error.error, DatabaseConnectionErrors::InvalidPath)),
+            Ok(_) => panic!("Expected a missing cluster file to be rejected"),
+        }
+    }
+
+    /// Needs the FoundationDB client library, but no FoundationDB server.
+    #[tokio::test]
+    async fn test_cluster_file_points_at_coordinator() {
+        let coordinator = EndpointStandIn::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fdb.cluster");
+        fs::write(&path, format!("test:standin@{}", coordinator.addr)).unwrap();
+        let manager = ConnectionManager::new(settings(
+            DatabaseType::FoundationDB,
+            path.to_str().unwrap().to_string(),
+            ClusterSettings {
+                transaction_timeout: Some(Duration::from_secs(5)),
+                ..Default::default()
+            },
+        ));
+        assert_contacted(manager, &coordinator, 1).await;
+    }
+}
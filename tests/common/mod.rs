@@ -0,0 +1,667 @@
+//! A local stand-in for a SurrealDB server used by the remote engine tests.
+//!
+//! The stand-in serves the same RPC protocols as a real server over an in-memory datastore,
+//! so remote engines can be exercised end to end without a SurrealDB binary or network access.
+#![allow(dead_code)]
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use bb8_surrealdb2::auth::Authentication;
+use bb8_surrealdb2::DatabaseSettings;
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+use bb8_surrealdb2::DatabaseType;
+use futures::{SinkExt, StreamExt};
+use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use revision::{revisioned, Revisioned};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use surrealdb::dbs::capabilities::Capabilities;
+use surrealdb::dbs::{Notification, Session};
+use surrealdb::iam;
+use surrealdb::kvs::Datastore;
+use surrealdb::rpc::format::Format;
+use surrealdb::rpc::{Data, RpcContext, RpcError, RpcProtocolV1, RpcProtocolV2};
+use surrealdb::sql::Value;
+use surrealdb::Uuid;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::AbortHandle;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+pub const ROOT_USER: &str = "root";
+pub const ROOT_PASS: &str = "root";
+
+/// Settings for an in-memory datastore, in the given namespace and the `test` database.
+#[cfg(feature = "kv-memory")]
+pub fn memory_settings(namespace: &str) -> DatabaseSettings {
+    DatabaseSettings::new_memory_db(namespace.to_string(), "test".to_string())
+}
+
+/// Settings that sign in as the root user to a server on the local host, in the `test`
+/// namespace and database. Tests change the fields they are about.
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+pub fn remote_settings(db_type: DatabaseType, port: u16) -> DatabaseSettings {
+    DatabaseSettings {
+        db_type,
+        auth: Authentication::root(ROOT_USER, ROOT_PASS),
+        port,
+        host: "127.0.0.1".to_string(),
+        path: "".to_string(),
+        namespace: "test".to_string(),
+        database_name: "test".to_string(),
+        tls: None,
+        cluster: None,
+    }
+}
+
+/// Settings for the stand-in, with the engine of its protocol. A stand-in with TLS is reached by
+/// the name on its certificate, and the test sets the CA bundle to trust it with.
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+pub fn stand_in_settings(server: &StandIn) -> DatabaseSettings {
+    let secure = server.tls.is_some();
+    let db_type = match (server.protocol, secure) {
+        #[cfg(feature = "kv-websocket")]
+        (Protocol::WebSocket, false) => DatabaseType::WebSocket,
+        #[cfg(feature = "kv-websocket")]
+        (Protocol::WebSocket, true) => DatabaseType::WebSocketSecure,
+        #[cfg(feature = "http")]
+        (Protocol::Http, false) => DatabaseType::Http,
+        #[cfg(feature = "http")]
+        (Protocol::Http, true) => DatabaseType::Https,
+        #[allow(unreachable_patterns)]
+        (protocol, _) => panic!("The engine for {:?} is not enabled", protocol),
+    };
+    let mut settings = remote_settings(db_type, server.port());
+    if secure {
+        settings.host = "localhost".to_string();
+    }
+    settings
+}
+
+/// The RPC transport served by a stand-in.
+#[derive(Clone, Copy, Debug)]
+pub enum Protocol {
+    Http,
+    WebSocket,
+}
+
+/// A running stand-in server. Every task it spawned is stopped when it is dropped.
+pub struct StandIn {
+    pub addr: SocketAddr,
+    pub kvs: Arc<Datastore>,
+    protocol: Protocol,
+    tls: Option<Arc<ServerConfig>>,
+    tasks: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+/// The state shared by every connection to a stand-in.
+struct Server {
+    kvs: Arc<Datastore>,
+    /// Sessions for verified `Authorization` headers, so that each HTTP request does not rehash
+    /// the password.
+    credentials: Mutex<HashMap<String, Session>>,
+    live_queries: LiveQueries,
+}
+
+/// The WebSocket connection that started each live query, to route its notifications to.
+type LiveQueries = Arc<Mutex<HashMap<Uuid, mpsc::UnboundedSender<Notification>>>>;
+
+impl StandIn {
+    /// Starts a plain HTTP stand-in on a free local port.
+    pub async fn http() -> StandIn {
+        StandIn::start(Protocol::Http, None).await
+    }
+
+    /// Starts a plain WebSocket stand-in on a free local port.
+    pub async fn ws() -> StandIn {
+        StandIn::start(Protocol::WebSocket, None).await
+    }
+
+    /// Starts a stand-in for the given protocol, wrapped in TLS when a server config is given.
+    pub async fn start(protocol: Protocol, tls: Option<Arc<ServerConfig>>) -> StandIn {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stand_in = StandIn {
+            addr: listener.local_addr().unwrap(),
+            kvs: Arc::new(datastore().await),
+            protocol,
+            tls,
+            tasks: Arc::new(Mutex::new(Vec::new())),
+        };
+        stand_in.listen(listener);
+        stand_in
+    }
+
+    /// Starts serving again on the same port and with the same data after `stop`,
+    /// as a server restart would.
+    pub async fn restart(&self) {
+        let listener = TcpListener::bind(self.addr).await.unwrap();
+        self.listen(listener);
+    }
+
+    fn listen(&self, listener: TcpListener) {
+        let protocol = self.protocol;
+        let server = Arc::new(Server {
+            kvs: self.kvs.clone(),
+            credentials: Mutex::new(HashMap::new()),
+            live_queries: Arc::new(Mutex::new(HashMap::new())),
+        });
+        let tasks = self.tasks.clone();
+        let notifications = self.kvs.notifications().unwrap();
+        let live_queries = server.live_queries.clone();
+        let route = tokio::spawn(async move {
+            while let Ok(notification) = notifications.recv().await {
+                if let Some(connection) = live_queries.lock().unwrap().get(&notification.id.0) {
+                    let _ = connection.send(notification);
+                }
+            }
+        });
+        tasks.lock().unwrap().push(route.abort_handle());
+        let accept = {
+            let tasks = tasks.clone();
+            let acceptor = self.tls.clone().map(TlsAcceptor::from);
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let server = server.clone();
+                    let acceptor = acceptor.clone();
+                    let task = tokio::spawn(async move {
+                        match acceptor {
+                            Some(acceptor) => {
+                                if let Ok(stream) = acceptor.accept(stream).await {
+                                    serve(protocol, stream, server).await;
+                                }
+                            }
+                            None => serve(protocol, stream, server).await,
+                        }
+                    });
+                    tasks.lock().unwrap().push(task.abort_handle());
+                }
+            })
+        };
+        tasks.lock().unwrap().push(accept.abort_handle());
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Runs SurrealQL directly against the datastore as the owner, for setting up users and data.
+    pub async fn execute(&self, sql: &str) {
+        for response in self
+            .kvs
+            .execute(sql, &Session::owner(), None)
+            .await
+            .unwrap()
+        {
+            response.result.unwrap();
+        }
+    }
+
+    /// Stops accepting connections and drops every open connection, as a server crash would.
+    pub fn stop(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for StandIn {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A stand-in for a cluster endpoint, such as a TiKV placement driver or a FoundationDB
+/// coordinator. It accepts connections and counts them without ever answering, which is enough
+/// to check which endpoint a client was pointed at.
+pub struct EndpointStandIn {
+    pub addr: SocketAddr,
+    accepted: Arc<AtomicUsize>,
+    task: AbortHandle,
+}
+
+impl EndpointStandIn {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        let task = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                // Hold the connection open, as a busy endpoint would.
+                connections.push(stream);
+            }
+        });
+        EndpointStandIn {
+            addr,
+            accepted,
+            task: task.abort_handle(),
+        }
+    }
+
+    /// The number of connections accepted so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for EndpointStandIn {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// An address on which nothing is listening.
+pub async fn closed_addr() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap()
+}
+
+/// A throwaway certificate authority with server and client certificates signed by it.
+/// The PEM files are written to a temporary directory that lives as long as this value.
+pub struct TlsMaterial {
+    dir: TempDir,
+    ca: CertificateDer<'static>,
+    server_chain: Vec<CertificateDer<'static>>,
+    server_key: Vec<u8>,
+}
+
+impl TlsMaterial {
+    pub fn generate() -> TlsMaterial {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "Stand-in CA");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let mut server_params =
+            CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+        server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        client_params
+            .distinguished_name
+            .push(DnType::CommonName, "Stand-in client");
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.path().join("client.pem"), client.pem()).unwrap();
+        std::fs::write(dir.path().join("client.key"), client_key.serialize_pem()).unwrap();
+        TlsMaterial {
+            dir,
+            ca: ca.der().clone(),
+            server_chain: vec![server.der().clone()],
+            server_key: server_key.serialize_der(),
+        }
+    }
+
+    /// The server side TLS config, optionally requiring a client certificate signed by the CA.
+    pub fn server_config(&self, require_client_certificate: bool) -> Arc<ServerConfig> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = if require_client_certificate {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.ca.clone()).unwrap();
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        let key = PrivateKeyDer::Pkcs8(self.server_key.clone().into());
+        Arc::new(
+            builder
+                .with_single_cert(self.server_chain.clone(), key)
+                .unwrap(),
+        )
+    }
+
+    pub fn ca_bundle(&self) -> String {
+        self.path("ca.pem")
+    }
+
+    pub fn client_certificate(&self) -> String {
+        self.path("client.pem")
+    }
+
+    pub fn client_key(&self) -> String {
+        self.path("client.key")
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.dir.path().join(name).to_string_lossy().into_owned()
+    }
+}
+
+/// Creates an in-memory datastore with authentication enabled and a root user.
+async fn datastore() -> Datastore {
+    let kvs = Datastore::new("memory")
+        .await
+        .unwrap()
+        .with_capabilities(Capabilities::all())
+        .with_auth_enabled(true)
+        .with_notifications();
+    kvs.bootstrap().await.unwrap();
+    kvs.initialise_credentials(ROOT_USER, ROOT_PASS)
+        .await
+        .unwrap();
+    kvs
+}
+
+/// The RPC state for an HTTP request or a WebSocket connection.
+struct RpcSession {
+    kvs: Arc<Datastore>,
+    lock: Arc<Semaphore>,
+    session: RwLock<Arc<Session>>,
+    /// Where the notifications of the live queries started on a WebSocket connection are sent.
+    live: Option<(LiveQueries, mpsc::UnboundedSender<Notification>)>,
+}
+
+impl RpcSession {
+    fn new(kvs: Arc<Datastore>, session: Session) -> RpcSession {
+        RpcSession {
+            kvs,
+            lock: Arc::new(Semaphore::new(1)),
+            session: RwLock::new(Arc::new(session)),
+            live: None,
+        }
+    }
+}
+
+impl RpcContext for RpcSession {
+    fn kvs(&self) -> &Datastore {
+        &self.kvs
+    }
+
+    fn lock(&self) -> Arc<Semaphore> {
+        self.lock.clone()
+    }
+
+    fn session(&self) -> Arc<Session> {
+        self.session.read().unwrap().clone()
+    }
+
+    fn set_session(&self, session: Arc<Session>) {
+        *self.session.write().unwrap() = session;
+    }
+
+    fn version_data(&self) -> Data {
+        Value::from(format!("surrealdb-{}", surrealdb::env::VERSION)).into()
+    }
+
+    const LQ_SUPPORT: bool = true;
+
+    async fn handle_live(&self, id: &Uuid) {
+        if let Some((live_queries, connection)) = &self.live {
+            live_queries.lock().unwrap().insert(*id, connection.clone());
+        }
+    }
+
+    async fn handle_kill(&self, id: &Uuid) {
+        if let Some((live_queries, _)) = &self.live {
+            live_queries.lock().unwrap().remove(id);
+        }
+    }
+
+    async fn cleanup_lqs(&self) {
+        if let Some((live_queries, connection)) = &self.live {
+            live_queries
+                .lock()
+                .unwrap()
+                .retain(|_, other| !other.same_channel(connection));
+        }
+    }
+}
+
+impl RpcProtocolV1 for RpcSession {}
+
+impl RpcProtocolV2 for RpcSession {}
+
+/// The RPC response envelope expected by the SurrealDB client.
+#[revisioned(revision = 1)]
+#[derive(Serialize)]
+struct RpcResponse {
+    id: Option<Value>,
+    result: Result<Data, Failure>,
+}
+
+#[revisioned(revision = 1)]
+#[derive(Serialize)]
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+impl From<RpcError> for Failure {
+    fn from(error: RpcError) -> Self {
+        let code = match error {
+            RpcError::ParseError => -32700,
+            RpcError::InvalidRequest => -32600,
+            RpcError::MethodNotFound => -32601,
+            RpcError::InvalidParams => -32602,
+            _ => -32000,
+        };
+        Failure {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Runs a single RPC request and encodes the response in the same format as the request.
+async fn execute(context: &RpcSession, format: Format, body: Vec<u8>) -> Vec<u8> {
+    let (id, result) = match format.req(body) {
+        Ok(request) => {
+            let result =
+                RpcContext::execute(context, request.version, request.method, request.params).await;
+            (request.id, result)
+        }
+        Err(error) => (None, Err(error)),
+    };
+    let response = RpcResponse {
+        id,
+        result: result.map_err(Failure::from),
+    };
+    encode(format, response)
+}
+
+/// Encodes a response in the given format.
+fn encode(format: Format, response: RpcResponse) -> Vec<u8> {
+    match format {
+        Format::Revision => {
+            let mut buffer = Vec::new();
+            response.serialize_revisioned(&mut buffer).unwrap();
+            buffer
+        }
+        _ => surrealdb::sql::serde::serialize(&response).unwrap(),
+    }
+}
+
+async fn serve<S>(protocol: Protocol, stream: S, server: Arc<Server>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match protocol {
+        Protocol::Http => serve_http(stream, server).await,
+        Protocol::WebSocket => serve_ws(stream, server).await,
+    }
+}
+
+/// Accepts the `revision` subprotocol requested by the SurrealDB client.
+// The error type is fixed by the tungstenite handshake callback.
+#[allow(clippy::result_large_err)]
+fn accept_revision(_: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+    response
+        .headers_mut()
+        .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("revision"));
+    Ok(response)
+}
+
+/// Serves RPC messages on a single WebSocket connection, which keeps its session between calls.
+async fn serve_ws<S>(stream: S, server: Arc<Server>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, accept_revision).await else {
+        return;
+    };
+    let (sender, mut notifications) = mpsc::unbounded_channel();
+    let mut context = RpcSession::new(server.kvs.clone(), Session::default().with_rt(true));
+    context.live = Some((server.live_queries.clone(), sender));
+    loop {
+        let response = tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(Message::Binary(body))) => {
+                    execute(&context, Format::Revision, body).await
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(notification) = notifications.recv() => encode(
+                Format::Revision,
+                RpcResponse {
+                    id: None,
+                    result: Ok(Data::Live(notification)),
+                },
+            ),
+        };
+        if socket.send(Message::Binary(response)).await.is_err() {
+            break;
+        }
+    }
+    context.cleanup_lqs().await;
+}
+
+/// Builds the session for an HTTP request from its SurrealDB headers.
+async fn http_session(server: &Server, headers: &HashMap<String, String>) -> Result<Session, ()> {
+    let kvs = server.kvs.as_ref();
+    let mut session = Session::default();
+    session.ns = headers.get("surreal-ns").cloned();
+    session.db = headers.get("surreal-db").cloned();
+    if let Some(authorization) = headers.get("authorization") {
+        let key = format!(
+            "{}|{:?}|{:?}|{:?}|{:?}",
+            authorization,
+            headers.get("surreal-auth-ns"),
+            headers.get("surreal-auth-db"),
+            session.ns,
+            session.db
+        );
+        if let Some(session) = server.credentials.lock().unwrap().get(&key) {
+            return Ok(session.clone());
+        }
+        if let Some(encoded) = authorization.strip_prefix("Basic ") {
+            let decoded = STANDARD.decode(encoded).map_err(|_| ())?;
+            let decoded = String::from_utf8(decoded).map_err(|_| ())?;
+            let (user, pass) = decoded.split_once(':').ok_or(())?;
+            iam::verify::basic(
+                kvs,
+                &mut session,
+                user,
+                pass,
+                headers.get("surreal-auth-ns").map(String::as_str),
+                headers.get("surreal-auth-db").map(String::as_str),
+            )
+            .await
+            .map_err(|_| ())?;
+        } else if let Some(token) = authorization.strip_prefix("Bearer ") {
+            iam::verify::token(kvs, &mut session, token)
+                .await
+                .map_err(|_| ())?;
+        }
+        server
+            .credentials
+            .lock()
+            .unwrap()
+            .insert(key, session.clone());
+    }
+    Ok(session)
+}
+
+/// Serves HTTP/1.1 requests on a single connection until the client closes it.
+async fn serve_http<S>(stream: S, server: Arc<Server>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        match stream.read_line(&mut request_line).await {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+        let length = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        if stream.read_exact(&mut body).await.is_err() {
+            return;
+        }
+        let (status, body) = match (method.as_str(), path.as_str()) {
+            ("GET", "/health") => ("200 OK", Vec::new()),
+            ("POST", "/rpc") => match http_session(&server, &headers).await {
+                Ok(session) => {
+                    let context = RpcSession::new(server.kvs.clone(), session);
+                    ("200 OK", execute(&context, Format::Bincode, body).await)
+                }
+                Err(_) => ("401 Unauthorized", Vec::new()),
+            },
+            _ => ("404 Not Found", Vec::new()),
+        };
+        let head = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/surrealdb\r\ncontent-length: {}\r\n\r\n",
+            status,
+            body.len()
+        );
+        let stream = stream.get_mut();
+        if stream.write_all(head.as_bytes()).await.is_err()
+            || stream.write_all(&body).await.is_err()
+            || stream.flush().await.is_err()
+        {
+            return;
+        }
+    }
+}
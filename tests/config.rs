@@ -0,0 +1,228 @@
+extern crate bb8;
+
+use bb8_surrealdb2::auth::Authentication;
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::{DatabaseSettings, DatabaseType};
+use std::env;
+
+/// Sets environment variables under a prefix that is unique to the calling test,
+/// so tests running in parallel do not see each other's variables.
+fn set_vars(prefix: &str, vars: &[(&str, &str)]) {
+    for (suffix, value) in vars {
+        env::set_var(format!("{}_{}", prefix, suffix), value);
+    }
+}
+
+fn assert_missing(prefix: &str, expected: &str) {
+    match DatabaseSettings::from_env(prefix) {
+        Err(DatabaseConnectionErrors::MissingEnvironmentVariable(name)) => {
+            assert_eq!(name, expected)
+        }
+        other => panic!("Expected {} to be missing, got {:?}", expected, other),
+    }
+}
+
+#[test]
+fn test_memory_from_toml() {
+    let settings: DatabaseSettings = toml::from_str(
+        r#"
+        db_type = "mem"
+        namespace = "app"
+        database_name = "main"
+        "#,
+    )
+    .unwrap();
+    assert!(matches!(settings.db_type, DatabaseType::Memory));
+    assert!(matches!(settings.auth, Authentication::None));
+    assert_eq!(settings.namespace, "app");
+    assert_eq!(settings.database_name, "main");
+    assert!(settings.tls.is_none());
+}
+
+#[test]
+fn test_missing_field_in_toml() {
+    let error = toml::from_str::<DatabaseSettings>(
+        r#"
+        db_type = "mem"
+        namespace = "app"
+        "#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("database_name"), "{}", error);
+}
+
+#[test]
+fn test_memory_from_env() {
+    set_vars(
+        "MEMORY_ENV",
+        &[("URL", "mem://"), ("NS", "app"), ("DB", "main")],
+    );
+    let settings = DatabaseSettings::from_env("MEMORY_ENV").unwrap();
+    assert!(matches!(settings.db_type, DatabaseType::Memory));
+    assert_eq!(settings.namespace, "app");
+    assert_eq!(settings.database_name, "main");
+}
+
+#[test]
+fn test_missing_env_vars() {
+    assert_missing("MISSING_ENV", "MISSING_ENV_URL");
+    set_vars("MISSING_ENV", &[("URL", "mem://")]);
+    assert_missing("MISSING_ENV", "MISSING_ENV_NS");
+    set_vars("MISSING_ENV", &[("NS", "app")]);
+    assert_missing("MISSING_ENV", "MISSING_ENV_DB");
+}
+
+#[test]
+fn test_invalid_url_env_var() {
+    set_vars("INVALID_URL_ENV", &[("URL", "postgres://localhost")]);
+    match DatabaseSettings::from_env("INVALID_URL_ENV") {
+        Err(DatabaseConnectionErrors::InvalidEnvironmentVariable { name, reason }) => {
+            assert_eq!(name, "INVALID_URL_ENV_URL");
+            assert!(reason.contains("postgres"), "{}", reason);
+        }
+        other => panic!("Expected an invalid variable, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "kv-websocket")]
+mod remote {
+    use super::*;
+
+    #[test]
+    fn test_remote_from_toml() {
+        let settings: DatabaseSettings = toml::from_str(
+            r#"
+            db_type = "wss"
+            host = "db.example.com"
+            port = 8000
+            namespace = "app"
+            database_name = "main"
+
+            [auth.database]
+            username = "app"
+            password = "secret"
+
+            [tls]
+            ca_bundle = "/etc/surrealdb/ca.pem"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(settings.db_type, DatabaseType::WebSocketSecure));
+        assert_eq!(settings.host, "db.example.com");
+        assert_eq!(settings.port, 8000);
+        assert!(matches!(
+            &settings.auth,
+            Authentication::Database { username, password } if username == "app" && password == "secret"
+        ));
+        let tls = settings.tls.as_ref().unwrap();
+        assert_eq!(tls.ca_bundle.as_deref(), Some("/etc/surrealdb/ca.pem"));
+        assert!(!tls.danger_accept_invalid_certificates);
+
+        // Settings written back out read in the same.
+        let written = toml::to_string(&settings).unwrap();
+        let read: DatabaseSettings = toml::from_str(&written).unwrap();
+        assert_eq!(format!("{:?}", read), format!("{:?}", settings));
+    }
+
+    #[test]
+    fn test_token_from_inline_toml() {
+        let settings: DatabaseSettings = toml::from_str(
+            r#"
+            db_type = "WebSocket"
+            host = "localhost"
+            port = 8000
+            namespace = "app"
+            database_name = "main"
+            auth = { token = "abc.def.ghi" }
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(settings.db_type, DatabaseType::WebSocket));
+        assert!(matches!(&settings.auth, Authentication::Token(token) if token == "abc.def.ghi"));
+    }
+
+    #[test]
+    fn test_credentials_from_env() {
+        set_vars(
+            "CREDENTIALS_ENV",
+            &[
+                ("URL", "ws://localhost:8000/app/main"),
+                ("AUTH", "namespace"),
+                ("USER", "app"),
+                ("PASS", "secret"),
+                ("CA_BUNDLE", "/etc/surrealdb/ca.pem"),
+            ],
+        );
+        let settings = DatabaseSettings::from_env("CREDENTIALS_ENV").unwrap();
+        assert_eq!(settings.host, "localhost");
+        assert_eq!(settings.namespace, "app");
+        assert!(matches!(
+            &settings.auth,
+            Authentication::Namespace { username, password } if username == "app" && password == "secret"
+        ));
+        assert_eq!(
+            settings.tls.unwrap().ca_bundle.as_deref(),
+            Some("/etc/surrealdb/ca.pem")
+        );
+    }
+
+    #[test]
+    fn test_env_credentials_replace_url_token() {
+        set_vars(
+            "URL_USER_ENV",
+            &[
+                ("URL", "ws://localhost:8000/app/main?auth=token&token=abc"),
+                ("USER", "root"),
+            ],
+        );
+        // The variables select root credentials, which need a password.
+        assert_missing("URL_USER_ENV", "URL_USER_ENV_PASS");
+        set_vars("URL_USER_ENV", &[("PASS", "secret")]);
+        let settings = DatabaseSettings::from_env("URL_USER_ENV").unwrap();
+        assert!(
+            matches!(&settings.auth, Authentication::Root { username, .. } if username == "root")
+        );
+    }
+
+    #[test]
+    fn test_record_access_from_env() {
+        set_vars(
+            "RECORD_ENV",
+            &[
+                (
+                    "URL",
+                    "ws://localhost:8000/app/main?auth=record&access=account&email=a%40b.c",
+                ),
+                ("ACCESS", "admin"),
+            ],
+        );
+        let settings = DatabaseSettings::from_env("RECORD_ENV").unwrap();
+        match &settings.auth {
+            Authentication::Record { access, params } => {
+                assert_eq!(access, "admin");
+                assert_eq!(params["email"], "a@b.c");
+            }
+            other => panic!("Expected record access, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_and_auth_from_env() {
+        set_vars(
+            "TOKEN_ENV",
+            &[("URL", "ws://localhost:8000/app/main"), ("AUTH", "token")],
+        );
+        assert_missing("TOKEN_ENV", "TOKEN_ENV_TOKEN");
+        set_vars("TOKEN_ENV", &[("TOKEN", "abc.def.ghi")]);
+        let settings = DatabaseSettings::from_env("TOKEN_ENV").unwrap();
+        assert!(matches!(&settings.auth, Authentication::Token(token) if token == "abc.def.ghi"));
+
+        set_vars("TOKEN_ENV", &[("AUTH", "scope")]);
+        match DatabaseSettings::from_env("TOKEN_ENV") {
+            Err(DatabaseConnectionErrors::InvalidEnvironmentVariable { name, .. }) => {
+                assert_eq!(name, "TOKEN_ENV_AUTH")
+            }
+            other => panic!("Expected an invalid variable, got {:?}", other),
+        }
+    }
+}
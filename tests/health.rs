@@ -0,0 +1,115 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::ManageConnection;
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::health::HealthCheck;
+use bb8_surrealdb2::session::SessionReset;
+use bb8_surrealdb2::ConnectionManager;
+use common::memory_settings;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn manager(namespace: &str, health_check: HealthCheck) -> ConnectionManager {
+    let settings = memory_settings(namespace);
+    ConnectionManager::new(settings).with_health_check(health_check)
+}
+
+fn assert_failed(error: DatabaseConnectionErrors, expected_check: &str, expected_reason: &str) {
+    match error {
+        DatabaseConnectionErrors::HealthCheckFailed { check, reason, .. } => {
+            assert_eq!(check, expected_check);
+            assert!(reason.contains(expected_reason), "{}", reason);
+        }
+        other => panic!("Expected a failed health check, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_builtin_checks_pass() {
+    for health_check in [HealthCheck::Ping, HealthCheck::Query, HealthCheck::Info] {
+        let manager = manager("builtin", health_check);
+        let mut connection = manager.connect().await.unwrap();
+        manager.is_valid(&mut connection).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_custom_check_failure_is_reported() {
+    let manager = manager(
+        "custom",
+        HealthCheck::custom(|client| async move {
+            let mut response = client.query("SELECT VALUE <string> id FROM ready").await?;
+            let ready: Vec<String> = response.take(0)?;
+            if ready.is_empty() {
+                return Err("no ready record".into());
+            }
+            Ok(())
+        }),
+    );
+    let mut connection = manager.connect().await.unwrap();
+    let error = manager.is_valid(&mut connection).await.unwrap_err();
+    assert_failed(error.error, "custom", "no ready record");
+    assert!(!connection.is_broken());
+
+    connection
+        .query("CREATE ready:one")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    manager.is_valid(&mut connection).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_slow_check_times_out() {
+    let manager = manager(
+        "timeout",
+        HealthCheck::custom(|_| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        }),
+    )
+    .with_health_check_timeout(Duration::from_millis(50));
+    let mut connection = manager.connect().await.unwrap();
+    let error = manager.is_valid(&mut connection).await.unwrap_err();
+    assert_failed(error.error, "custom", "no answer within 50ms");
+}
+
+#[tokio::test]
+async fn test_slow_session_reset_counts_against_the_timeout() {
+    let connected = Arc::new(AtomicBool::new(false));
+    let manager = manager("slow_reset", HealthCheck::Ping)
+        .with_health_check_timeout(Duration::from_millis(50))
+        .with_session_reset(SessionReset::default())
+        .on_connect(move |_, _| {
+            // Fast when connecting, slow when the session reset runs the hook again.
+            let again = connected.swap(true, Ordering::SeqCst);
+            Box::pin(async move {
+                if again {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+                Ok(())
+            })
+        });
+    let mut connection = manager.connect().await.unwrap();
+    connection.set("user", "alice").await.unwrap();
+    let error = manager.is_valid(&mut connection).await.unwrap_err();
+    assert_failed(error.error, "ping", "no answer within 50ms");
+}
+
+#[tokio::test]
+async fn test_broken_connection_fails_without_running_the_check() {
+    let manager = manager(
+        "broken",
+        HealthCheck::custom(|_| async { panic!("The check should not run") }),
+    );
+    let mut connection = manager.connect().await.unwrap();
+    connection.mark_broken();
+    let error = manager.is_valid(&mut connection).await.unwrap_err();
+    assert_failed(error.error, "custom", "the connection is broken");
+}
@@ -0,0 +1,81 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::{ManageConnection, Pool};
+use bb8_surrealdb2::errors::{DatabaseConnectionErrors, ErrorKind};
+use bb8_surrealdb2::retry::Backoff;
+use bb8_surrealdb2::ConnectionManager;
+use common::memory_settings;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_on_connect_sets_session_parameters() {
+    let manager = ConnectionManager::new(memory_settings("acme")).on_connect(|client, settings| {
+        Box::pin(async move {
+            client.set("tenant", settings.namespace.clone()).await?;
+            client.query("RETURN 1").await?.check()?;
+            Ok(())
+        })
+    });
+    let connection = manager.connect().await.unwrap();
+    let tenant: Option<String> = connection
+        .query("RETURN $tenant")
+        .await
+        .unwrap()
+        .take(0)
+        .unwrap();
+    assert_eq!(tenant.as_deref(), Some("acme"));
+}
+
+#[tokio::test]
+async fn test_on_connect_runs_for_every_connection() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    let manager = ConnectionManager::new(memory_settings("every")).on_connect(move |_, _| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async { Ok(()) })
+    });
+    let pool = Pool::builder()
+        .max_size(3)
+        .min_idle(Some(3))
+        .build(manager)
+        .await
+        .unwrap();
+    let _connections = (
+        pool.get().await.unwrap(),
+        pool.get().await.unwrap(),
+        pool.get().await.unwrap(),
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_on_connect_error_fails_the_connection() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    let manager = ConnectionManager::new(memory_settings("failing"))
+        .with_backoff(Backoff {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+        })
+        .on_connect(move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Err("the tenant is not provisioned".into()) })
+        });
+    let error = manager.connect().await.err().unwrap();
+    match &error.error {
+        DatabaseConnectionErrors::OnConnectFailed(cause) => {
+            assert_eq!(cause.to_string(), "the tenant is not provisioned")
+        }
+        other => panic!("Expected the hook to fail, got {:?}", other),
+    }
+    assert_eq!(error.kind(), ErrorKind::Server);
+    // Failures that are not transient are not retried.
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
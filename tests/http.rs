@@ -0,0 +1,125 @@
+#![cfg(feature = "http")]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::{ManageConnection, Pool};
+use bb8_surrealdb2::auth::Authentication;
+use bb8_surrealdb2::errors::{ConnectionError, DatabaseConnectionErrors};
+use bb8_surrealdb2::tls::TlsSettings;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+use common::{remote_settings, Protocol, StandIn, TlsMaterial, ROOT_PASS, ROOT_USER};
+use std::collections::BTreeMap;
+
+fn settings(db_type: DatabaseType, port: u16, password: &str) -> DatabaseSettings {
+    DatabaseSettings {
+        auth: Authentication::root(ROOT_USER, password),
+        ..remote_settings(db_type, port)
+    }
+}
+
+#[tokio::test]
+async fn test_http_pool_round_trip() {
+    let server = StandIn::http().await;
+    let manager = ConnectionManager::new(settings(DatabaseType::Http, server.port(), ROOT_PASS));
+    let pool = Pool::builder().max_size(2).build(manager).await.unwrap();
+
+    let writer = pool.get().await.unwrap();
+    let reader = pool.get().await.unwrap();
+    writer
+        .query("CREATE person:tobie SET name = 'Tobie'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    let mut response = reader
+        .query("SELECT VALUE name FROM person:tobie")
+        .await
+        .unwrap();
+    let names: Vec<String> = response.take(0).unwrap();
+    assert_eq!(names, vec!["Tobie".to_string()]);
+
+    // Queries must run in the configured namespace and database.
+    let mut response = reader
+        .query("RETURN [session::ns(), session::db()]")
+        .await
+        .unwrap();
+    let selected: Vec<String> = response.take(0).unwrap();
+    assert_eq!(selected, vec!["test".to_string(), "test".to_string()]);
+}
+
+#[tokio::test]
+async fn test_http_wrong_password_fails_authentication() {
+    let server = StandIn::http().await;
+    let manager = ConnectionManager::new(settings(DatabaseType::Http, server.port(), "wrong"));
+    match manager.connect().await {
+        Err(ConnectionError {
+            error: DatabaseConnectionErrors::AuthenticationFailed(_),
+            ..
+        }) => {}
+        other => panic!("Expected an authentication failure, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn test_http_record_access() {
+    let server = StandIn::http().await;
+    server
+        .execute(
+            "USE NS test DB test;
+            DEFINE ACCESS account ON DATABASE TYPE RECORD
+                SIGNIN (SELECT * FROM user WHERE email = $email AND pass = $pass);
+            CREATE user:alice SET email = 'alice@example.com', pass = 'secret';",
+        )
+        .await;
+    let mut manager_settings = settings(DatabaseType::Http, server.port(), ROOT_PASS);
+    manager_settings.auth = Authentication::Record {
+        access: "account".to_string(),
+        params: BTreeMap::from([
+            ("email".to_string(), "alice@example.com".to_string()),
+            ("pass".to_string(), "secret".to_string()),
+        ]),
+    };
+    let connection = ConnectionManager::new(manager_settings)
+        .connect()
+        .await
+        .unwrap();
+    let mut response = connection.query("RETURN <string> $auth").await.unwrap();
+    let record: Option<String> = response.take(0).unwrap();
+    assert_eq!(record, Some("user:alice".to_string()));
+}
+
+#[tokio::test]
+async fn test_https_requires_tls() {
+    // The stand-in only speaks plain HTTP, so the TLS handshake for https:// must fail.
+    let server = StandIn::http().await;
+    let manager = ConnectionManager::new(settings(DatabaseType::Https, server.port(), ROOT_PASS));
+    match manager.connect().await {
+        Err(ConnectionError {
+            error: DatabaseConnectionErrors::ConnectFailed(_),
+            ..
+        }) => {}
+        other => panic!("Expected a connect failure, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn test_https_with_ca_bundle() {
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::Http, Some(material.server_config(false))).await;
+    let mut settings = settings(DatabaseType::Https, server.port(), ROOT_PASS);
+    settings.host = "localhost".to_string();
+    settings.tls = Some(TlsSettings {
+        ca_bundle: Some(material.ca_bundle()),
+        ..Default::default()
+    });
+    let manager = ConnectionManager::new(settings);
+    let connection = manager.connect().await.unwrap();
+    let mut response = connection
+        .query("RETURN [session::ns(), session::db()]")
+        .await
+        .unwrap();
+    let selected: Vec<String> = response.take(0).unwrap();
+    assert_eq!(selected, vec!["test".to_string(), "test".to_string()]);
+}
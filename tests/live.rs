@@ -0,0 +1,224 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::Pool;
+use bb8_surrealdb2::errors::LiveQueryError;
+use bb8_surrealdb2::live::{LiveEvent, LiveSubscription};
+use bb8_surrealdb2::ConnectionManager;
+use common::memory_settings;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::time::Duration;
+use surrealdb::Action;
+use tokio::time::timeout;
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    name: String,
+}
+
+async fn pool(namespace: &str) -> Pool<ConnectionManager> {
+    recycling_pool(ConnectionManager::new(memory_settings(namespace))).await
+}
+
+/// A pool whose connections are recycled soon after they are opened.
+async fn recycling_pool(manager: ConnectionManager) -> Pool<ConnectionManager> {
+    manager
+        .pool_builder()
+        .max_lifetime(Some(Duration::from_millis(50)))
+        .build(manager)
+        .await
+        .unwrap()
+}
+
+async fn create(pool: &Pool<ConnectionManager>, name: &str) {
+    pool.get()
+        .await
+        .unwrap()
+        .query("CREATE item SET name = $name")
+        .bind(("name", name.to_string()))
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+}
+
+async fn next_event(subscription: &mut LiveSubscription<Item>) -> LiveEvent<Item> {
+    timeout(Duration::from_secs(5), subscription.next())
+        .await
+        .expect("No event within 5 seconds")
+        .expect("The subscription ended")
+        .unwrap()
+}
+
+async fn next_created(subscription: &mut LiveSubscription<Item>) -> String {
+    match next_event(subscription).await {
+        LiveEvent::Notification(notification) => {
+            assert_eq!(notification.action, Action::Create);
+            notification.data.name
+        }
+        other => panic!("Expected a notification, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reconnect_is_marked_and_resubscribes() {
+    let pool = pool("reconnect").await;
+    let mut items = LiveSubscription::<Item>::subscribe(&pool, "LIVE SELECT * FROM item")
+        .await
+        .unwrap();
+    items.reconnect();
+    assert!(matches!(
+        next_event(&mut items).await,
+        LiveEvent::Disconnected
+    ));
+    assert!(matches!(
+        next_event(&mut items).await,
+        LiveEvent::Reconnected
+    ));
+    create(&pool, "after").await;
+    assert_eq!(next_created(&mut items).await, "after");
+}
+
+#[tokio::test]
+async fn test_invalid_query_is_reported() {
+    let pool = pool("invalid").await;
+    match LiveSubscription::<Item>::subscribe(&pool, "LIVE SELECT FROM").await {
+        Err(LiveQueryError::Query(_)) => {}
+        Err(error) => panic!("Expected a query error, got {:?}", error),
+        Ok(_) => panic!("Expected the query to be rejected"),
+    }
+}
+
+/// Waits for `Disconnected`, skipping the errors of the stream on the lost connection.
+#[cfg(feature = "kv-websocket")]
+async fn disconnected(subscription: &mut LiveSubscription<Item>) {
+    loop {
+        match timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .expect("No event within 5 seconds")
+            .expect("The subscription ended")
+        {
+            Ok(LiveEvent::Disconnected) => return,
+            Err(LiveQueryError::Query(_)) => continue,
+            other => panic!("Expected to be disconnected, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_notifications_outlive_pooled_connections() {
+    use common::{stand_in_settings, StandIn};
+    use tokio::time::sleep;
+
+    let server = StandIn::ws().await;
+    let pool = recycling_pool(ConnectionManager::new(stand_in_settings(&server))).await;
+    let mut items = LiveSubscription::<Item>::subscribe(&pool, "LIVE SELECT * FROM item")
+        .await
+        .unwrap();
+    create(&pool, "first").await;
+    assert_eq!(next_created(&mut items).await, "first");
+    // The pooled connections are recycled, but the subscription has its own.
+    sleep(Duration::from_millis(200)).await;
+    create(&pool, "second").await;
+    assert_eq!(next_created(&mut items).await, "second");
+    // The live query is lost with the connection, so it only notifies again once it has been
+    // run on the new one.
+    items.reconnect();
+    assert!(matches!(
+        next_event(&mut items).await,
+        LiveEvent::Disconnected
+    ));
+    assert!(matches!(
+        next_event(&mut items).await,
+        LiveEvent::Reconnected
+    ));
+    sleep(Duration::from_millis(200)).await;
+    create(&pool, "third").await;
+    assert_eq!(next_created(&mut items).await, "third");
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_lost_websocket_is_marked_and_resubscribed() {
+    use bb8_surrealdb2::retry::Backoff;
+    use common::{stand_in_settings, StandIn};
+
+    let server = StandIn::ws().await;
+    let manager = ConnectionManager::new(stand_in_settings(&server))
+        .with_liveness_interval(Duration::from_millis(200))
+        .with_backoff(Backoff::none());
+    let pool = manager.pool_builder().build(manager).await.unwrap();
+    let backoff = Backoff {
+        max_retries: 50,
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(100),
+    };
+    let mut items =
+        LiveSubscription::<Item>::subscribe_with(&pool, "LIVE SELECT * FROM item", &backoff)
+            .await
+            .unwrap();
+    create(&pool, "before").await;
+    assert_eq!(next_created(&mut items).await, "before");
+
+    server.stop();
+    disconnected(&mut items).await;
+    // Wait for an attempt to fail while the server is down, which is passed on as an error.
+    match timeout(Duration::from_secs(5), items.next()).await {
+        Ok(Some(Err(LiveQueryError::Connection(_)))) => {}
+        other => panic!("Expected a failed attempt, got {:?}", other),
+    }
+    server.restart().await;
+    loop {
+        match timeout(Duration::from_secs(5), items.next())
+            .await
+            .expect("No event within 5 seconds")
+            .expect("The subscription ended")
+        {
+            Ok(LiveEvent::Reconnected) => break,
+            Err(LiveQueryError::Connection(_)) => continue,
+            other => panic!("Expected to be resubscribed, got {:?}", other),
+        }
+    }
+    create(&pool, "after").await;
+    assert_eq!(next_created(&mut items).await, "after");
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_subscription_ends_when_retries_run_out() {
+    use bb8_surrealdb2::retry::Backoff;
+    use common::{stand_in_settings, StandIn};
+
+    let server = StandIn::ws().await;
+    let manager = ConnectionManager::new(stand_in_settings(&server))
+        .with_liveness_interval(Duration::from_millis(200))
+        .with_backoff(Backoff::none());
+    let pool = manager.pool_builder().build(manager).await.unwrap();
+    let backoff = Backoff {
+        max_retries: 2,
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+    };
+    let mut items =
+        LiveSubscription::<Item>::subscribe_with(&pool, "LIVE SELECT * FROM item", &backoff)
+            .await
+            .unwrap();
+    server.stop();
+    disconnected(&mut items).await;
+    // The first attempt and both retries fail, and then the stream ends.
+    for _ in 0..3 {
+        assert!(matches!(
+            timeout(Duration::from_secs(5), items.next()).await,
+            Ok(Some(Err(LiveQueryError::Connection(_))))
+        ));
+    }
+    assert!(timeout(Duration::from_secs(5), items.next())
+        .await
+        .unwrap()
+        .is_none());
+}
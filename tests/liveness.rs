@@ -0,0 +1,117 @@
+#![cfg(any(feature = "kv-websocket", feature = "http"))]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::Pool;
+use bb8_surrealdb2::connection::SurrealConnection;
+use bb8_surrealdb2::{ConnectionManager, DatabaseType};
+use common::{remote_settings, StandIn};
+use std::time::Duration;
+use tokio::time::{sleep, timeout, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(200);
+
+fn manager(db_type: DatabaseType, port: u16, interval: Duration) -> ConnectionManager {
+    ConnectionManager::new(remote_settings(db_type, port)).with_liveness_interval(interval)
+}
+
+/// Waits for the background ping to notice that the connection is broken.
+async fn wait_until_broken(connection: &SurrealConnection) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !connection.is_broken() {
+        assert!(
+            Instant::now() < deadline,
+            "The connection was not reported as broken"
+        );
+        sleep(INTERVAL / 2).await;
+    }
+}
+
+/// Kills the stand-in while a connection is checked out, checks that the pool discards the
+/// connection instead of reusing it, and that the pool recovers once the server is back.
+async fn assert_discarded_after_restart(db_type: DatabaseType, server: StandIn) {
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager(db_type, server.port(), INTERVAL))
+        .await
+        .unwrap();
+    {
+        let connection = pool.get().await.unwrap();
+        connection
+            .query("CREATE person:tobie SET name = 'Tobie'")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        assert!(!connection.is_broken());
+
+        server.stop();
+        wait_until_broken(&connection).await;
+    }
+    // Returning the broken connection closes it.
+    assert_eq!(pool.state().connections, 0);
+
+    server.restart().await;
+    let connection = timeout(Duration::from_secs(10), pool.get())
+        .await
+        .unwrap()
+        .unwrap();
+    let mut response = connection
+        .query("SELECT VALUE name FROM person:tobie")
+        .await
+        .unwrap();
+    let names: Vec<String> = response.take(0).unwrap();
+    assert_eq!(names, vec!["Tobie".to_string()]);
+    assert!(!connection.is_broken());
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_ws_connection_discarded_after_server_stops() {
+    assert_discarded_after_restart(DatabaseType::WebSocket, StandIn::ws().await).await;
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_http_connection_discarded_after_server_stops() {
+    assert_discarded_after_restart(DatabaseType::Http, StandIn::http().await).await;
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_observed_connection_error_marks_broken() {
+    use bb8::ManageConnection;
+
+    let server = StandIn::http().await;
+    // Keep the background ping out of the way, so only the observed error can mark the connection.
+    let manager = manager(DatabaseType::Http, server.port(), Duration::from_secs(3600));
+    let mut connection = manager.connect().await.unwrap();
+
+    // A failed query leaves the connection usable.
+    let result = connection.query("THROW 'failed'").await.unwrap().check();
+    assert!(connection.observe(result).is_err());
+    assert!(!manager.has_broken(&mut connection));
+
+    server.stop();
+    assert!(connection.observe(connection.health().await).is_err());
+    assert!(manager.has_broken(&mut connection));
+    // A connection that is known to be broken fails validation without a round trip.
+    assert!(manager.is_valid(&mut connection).await.is_err());
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_marked_connection_is_not_reused() {
+    let server = StandIn::ws().await;
+    let pool = Pool::builder()
+        .max_size(1)
+        .build(manager(DatabaseType::WebSocket, server.port(), INTERVAL))
+        .await
+        .unwrap();
+    pool.get().await.unwrap().mark_broken();
+    assert_eq!(pool.state().connections, 0);
+    let connection = pool.get().await.unwrap();
+    assert!(!connection.is_broken());
+}
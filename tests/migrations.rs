@@ -0,0 +1,191 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::Pool;
+use bb8_surrealdb2::errors::MigrationError;
+use bb8_surrealdb2::migrations::{Migration, Migrator};
+use bb8_surrealdb2::ConnectionManager;
+use common::memory_settings;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+
+async fn pool() -> Pool<ConnectionManager> {
+    let settings = memory_settings("migrations");
+    Pool::builder()
+        .max_size(1)
+        .build(ConnectionManager::new(settings))
+        .await
+        .unwrap()
+}
+
+fn migrations() -> Vec<Migration> {
+    vec![
+        Migration::new(
+            2,
+            "add_email",
+            "DEFINE FIELD email ON user TYPE string; DEFINE INDEX user_email ON user FIELDS email UNIQUE;",
+        ),
+        Migration::new(1, "create_user", "DEFINE TABLE user SCHEMAFULL;"),
+    ]
+}
+
+fn versions(migrations: Vec<&Migration>) -> Vec<u64> {
+    migrations
+        .iter()
+        .map(|migration| migration.version)
+        .collect()
+}
+
+async fn applied_versions(pool: &Pool<ConnectionManager>) -> Vec<u64> {
+    let connection = pool.get().await.unwrap();
+    let mut response = connection
+        .query("SELECT VALUE meta::id(id) FROM _migrations")
+        .await
+        .unwrap();
+    let mut versions: Vec<u64> = response.take(0).unwrap();
+    versions.sort();
+    versions
+}
+
+async fn has_table(pool: &Pool<ConnectionManager>, table: &str) -> bool {
+    let connection = pool.get().await.unwrap();
+    let mut response = connection.query("INFO FOR DB").await.unwrap();
+    let tables: Option<BTreeMap<String, String>> = response.take("tables").unwrap();
+    tables.unwrap_or_default().contains_key(table)
+}
+
+#[tokio::test]
+async fn test_run_applies_pending_migrations_in_order() {
+    let pool = pool().await;
+    let migrator = Migrator::new(migrations()).unwrap();
+    assert_eq!(versions(migrator.run(&pool).await.unwrap()), vec![1, 2]);
+    assert_eq!(applied_versions(&pool).await, vec![1, 2]);
+    assert!(has_table(&pool, "user").await);
+
+    // A second run finds nothing to do, and a new migration is applied on its own.
+    assert!(migrator.run(&pool).await.unwrap().is_empty());
+    let mut migrations = migrations();
+    migrations.push(Migration::new(3, "create_team", "DEFINE TABLE team;"));
+    let migrator = Migrator::new(migrations).unwrap();
+    assert_eq!(versions(migrator.run(&pool).await.unwrap()), vec![3]);
+    assert_eq!(applied_versions(&pool).await, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_dry_run_does_not_apply() {
+    let pool = pool().await;
+    let migrator = Migrator::new(migrations()).unwrap();
+    assert_eq!(versions(migrator.dry_run(&pool).await.unwrap()), vec![1, 2]);
+    assert!(applied_versions(&pool).await.is_empty());
+    assert!(!has_table(&pool, "user").await);
+}
+
+#[tokio::test]
+async fn test_failed_migration_is_rolled_back() {
+    let pool = pool().await;
+    let mut migrations = migrations();
+    migrations.push(Migration::new(
+        3,
+        "broken",
+        "DEFINE TABLE team; THROW 'broken migration';",
+    ));
+    let migrator = Migrator::new(migrations).unwrap();
+    match migrator.run(&pool).await {
+        Err(
+            error @ MigrationError::Failed {
+                version,
+                rolled_back,
+                ..
+            },
+        ) => {
+            assert_eq!(version, 3);
+            assert!(rolled_back);
+            assert_eq!(
+                error.to_string(),
+                "MigrationError: Migration 3 `broken` failed and was rolled back"
+            );
+            assert!(error.source().is_some());
+        }
+        other => panic!("Expected the migration to fail, got {:?}", other),
+    }
+    assert_eq!(applied_versions(&pool).await, vec![1, 2]);
+    assert!(!has_table(&pool, "team").await);
+}
+
+#[tokio::test]
+async fn test_changed_and_unknown_migrations_are_detected() {
+    let pool = pool().await;
+    Migrator::new(migrations())
+        .unwrap()
+        .run(&pool)
+        .await
+        .unwrap();
+
+    let mut changed = migrations();
+    changed[1].script = "DEFINE TABLE user SCHEMALESS;".to_string();
+    match Migrator::new(changed).unwrap().dry_run(&pool).await {
+        Err(MigrationError::ChecksumMismatch { version, name }) => {
+            assert_eq!(version, 1);
+            assert_eq!(name, "create_user");
+        }
+        other => panic!("Expected a checksum mismatch, got {:?}", other),
+    }
+
+    let mut removed = migrations();
+    removed.remove(0);
+    assert!(matches!(
+        Migrator::new(removed).unwrap().run(&pool).await,
+        Err(MigrationError::UnknownVersion(2))
+    ));
+}
+
+#[test]
+fn test_duplicate_versions_are_rejected() {
+    let mut migrations = migrations();
+    migrations.push(Migration::new(1, "again", "DEFINE TABLE again;"));
+    assert!(matches!(
+        Migrator::new(migrations),
+        Err(MigrationError::DuplicateVersion(1))
+    ));
+}
+
+#[tokio::test]
+async fn test_from_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("0002_add_email.surql"),
+        &migrations()[0].script,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("0001_create_user.surql"),
+        &migrations()[1].script,
+    )
+    .unwrap();
+    fs::write(dir.path().join("README.md"), "Not a migration").unwrap();
+    let migrator = Migrator::from_dir(dir.path()).unwrap();
+    let names: Vec<&str> = migrator
+        .migrations()
+        .map(|migration| migration.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["create_user", "add_email"]);
+
+    // Files are checksummed the same way as embedded scripts.
+    let pool = pool().await;
+    Migrator::new(migrations())
+        .unwrap()
+        .run(&pool)
+        .await
+        .unwrap();
+    assert!(migrator.dry_run(&pool).await.unwrap().is_empty());
+
+    fs::write(dir.path().join("create_team.surql"), "DEFINE TABLE team;").unwrap();
+    match Migrator::from_dir(dir.path()) {
+        Err(MigrationError::InvalidFileName(name)) => assert_eq!(name, "create_team.surql"),
+        other => panic!("Expected an invalid file name, got {:?}", other),
+    }
+}
@@ -0,0 +1,113 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::ManageConnection;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings};
+
+const NAMESPACE: &str = "matrix_ns";
+const DATABASE: &str = "matrix_db";
+
+/// Every engine must run queries in the configured namespace and database.
+async fn assert_selected(settings: DatabaseSettings) {
+    let manager = ConnectionManager::new(settings);
+    let connection = manager.connect().await.unwrap();
+    let mut response = connection
+        .query("RETURN [session::ns(), session::db()]")
+        .await
+        .unwrap();
+    let selected: Vec<String> = response.take(0).unwrap();
+    assert_eq!(selected, vec![NAMESPACE.to_string(), DATABASE.to_string()]);
+}
+
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+fn stand_in_settings(
+    server: &common::StandIn,
+    tls: Option<bb8_surrealdb2::tls::TlsSettings>,
+) -> DatabaseSettings {
+    DatabaseSettings {
+        namespace: NAMESPACE.to_string(),
+        database_name: DATABASE.to_string(),
+        tls,
+        ..common::stand_in_settings(server)
+    }
+}
+
+#[cfg(any(feature = "kv-websocket", feature = "http"))]
+fn trusted(material: &common::TlsMaterial) -> Option<bb8_surrealdb2::tls::TlsSettings> {
+    Some(bb8_surrealdb2::tls::TlsSettings {
+        ca_bundle: Some(material.ca_bundle()),
+        ..Default::default()
+    })
+}
+
+#[cfg(feature = "kv-memory")]
+#[tokio::test]
+async fn test_memory_selects_namespace() {
+    assert_selected(DatabaseSettings {
+        database_name: DATABASE.to_string(),
+        ..common::memory_settings(NAMESPACE)
+    })
+    .await;
+}
+
+#[cfg(feature = "kv-rocksdb")]
+#[tokio::test]
+async fn test_file_selects_namespace() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_selected(DatabaseSettings::new_file_db(
+        dir.path().to_string_lossy().into_owned(),
+        NAMESPACE.to_string(),
+        DATABASE.to_string(),
+    ))
+    .await;
+}
+
+#[cfg(feature = "kv-surreal")]
+#[tokio::test]
+async fn test_surrealkv_selects_namespace() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_selected(DatabaseSettings::new_surrealkv_db(
+        dir.path().to_string_lossy().into_owned(),
+        NAMESPACE.to_string(),
+        DATABASE.to_string(),
+        false,
+    ))
+    .await;
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_websocket_selects_namespace() {
+    let server = common::StandIn::ws().await;
+    assert_selected(stand_in_settings(&server, None)).await;
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_websocket_secure_selects_namespace() {
+    use common::{Protocol, StandIn, TlsMaterial};
+
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::WebSocket, Some(material.server_config(false))).await;
+    assert_selected(stand_in_settings(&server, trusted(&material))).await;
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_http_selects_namespace() {
+    let server = common::StandIn::http().await;
+    assert_selected(stand_in_settings(&server, None)).await;
+}
+
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_https_selects_namespace() {
+    use common::{Protocol, StandIn, TlsMaterial};
+
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::Http, Some(material.server_config(false))).await;
+    assert_selected(stand_in_settings(&server, trusted(&material))).await;
+}
@@ -0,0 +1,76 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::{build_pool, global_pool, ConnectionManager};
+use common::memory_settings;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_build_pool_with_memory_defaults() {
+    let pool = build_pool(memory_settings("defaults")).await.unwrap();
+    // Embedded pools hold four connections.
+    let mut connections = Vec::new();
+    for _ in 0..4 {
+        connections.push(pool.get().await.unwrap());
+    }
+    assert_eq!(pool.state().connections, 4);
+    connections[0]
+        .query("CREATE pooled:one")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    let mut response = connections[3]
+        .query("SELECT VALUE <string> id FROM pooled")
+        .await
+        .unwrap();
+    let ids: Vec<String> = response.take(0).unwrap();
+    assert_eq!(ids, vec!["pooled:one".to_string()]);
+}
+
+#[tokio::test]
+async fn test_build_pool_checks_settings() {
+    match build_pool(memory_settings("")).await {
+        Err(error) => assert!(matches!(
+            error.error,
+            DatabaseConnectionErrors::InvalidNamespace
+        )),
+        Ok(_) => panic!("Expected the empty namespace to be rejected"),
+    }
+}
+
+#[tokio::test]
+async fn test_pool_builder_defaults_can_be_changed() {
+    let manager = ConnectionManager::new(memory_settings("changed"));
+    let pool = manager
+        .pool_builder()
+        .max_size(1)
+        .connection_timeout(Duration::from_millis(100))
+        .build(manager)
+        .await
+        .unwrap();
+    let _held = pool.get().await.unwrap();
+    assert!(pool.get().await.is_err());
+}
+
+#[tokio::test]
+async fn test_global_pool_is_built_once() {
+    // A failed first call leaves the pool unset.
+    assert!(
+        global_pool(|| async { Err(DatabaseConnectionErrors::InvalidNamespace) })
+            .await
+            .is_err()
+    );
+    let pool = global_pool(|| async { Ok(memory_settings("global")) })
+        .await
+        .unwrap();
+    let again = global_pool(|| async { panic!("The settings should only be read once") })
+        .await
+        .unwrap();
+    assert!(std::ptr::eq(pool, again));
+    pool.get().await.unwrap().health().await.unwrap();
+}
@@ -0,0 +1,118 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8_surrealdb2::build_pool;
+use bb8_surrealdb2::repository::{Repository, Table};
+use common::memory_settings;
+use serde::{Deserialize, Serialize};
+use surrealdb::opt::{PatchOp, PatchOps};
+use surrealdb::sql::Thing;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct Person {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Thing>,
+    name: String,
+    age: u32,
+}
+
+fn person(name: &str, age: u32) -> Person {
+    Person {
+        id: None,
+        name: name.to_string(),
+        age,
+    }
+}
+
+async fn people(namespace: &str) -> Table<Person> {
+    let pool = build_pool(memory_settings(namespace)).await.unwrap();
+    Table::new(pool, "person")
+}
+
+#[tokio::test]
+async fn test_crud() {
+    let people = people("crud").await;
+    let created = people.create(person("Tobie", 30)).await.unwrap();
+    let id = created.id.clone().unwrap().id.to_raw();
+    assert_eq!(people.get_by_id(&id).await.unwrap(), Some(created.clone()));
+
+    let updated = people
+        .update(&id, person("Tobie", 31))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.age, 31);
+    assert_eq!(people.get_all().await.unwrap(), vec![updated.clone()]);
+
+    assert_eq!(people.delete(&id).await.unwrap(), Some(updated));
+    assert_eq!(people.get_by_id(&id).await.unwrap(), None);
+    assert_eq!(people.update(&id, person("Tobie", 32)).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_upsert_merge_and_patch() {
+    let people = people("changes").await;
+    let upserted = people.upsert("jaime", person("Jaime", 40)).await.unwrap();
+    assert_eq!(upserted.id.unwrap().to_string(), "person:jaime");
+
+    #[derive(Serialize)]
+    struct Birthday {
+        age: u32,
+    }
+    let merged = people
+        .merge("jaime", Birthday { age: 41 })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((merged.name.as_str(), merged.age), ("Jaime", 41));
+
+    let patched = people
+        .patch("jaime", PatchOp::replace("/name", "James"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((patched.name.as_str(), patched.age), ("James", 41));
+
+    let patched = people
+        .patch(
+            "jaime",
+            PatchOps::new()
+                .replace("/name", "Jaime")
+                .replace("/age", 42),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((patched.name.as_str(), patched.age), ("Jaime", 42));
+}
+
+#[tokio::test]
+async fn test_count_and_page() {
+    let people = people("pages").await;
+    assert_eq!(people.count().await.unwrap(), 0);
+    for (key, name) in ["a", "b", "c", "d", "e"]
+        .iter()
+        .zip(["Ann", "Bob", "Cy", "Di", "Ed"])
+    {
+        people.upsert(*key, person(name, 20)).await.unwrap();
+    }
+    assert_eq!(people.count().await.unwrap(), 5);
+    let names =
+        |page: Vec<Person>| -> Vec<String> { page.into_iter().map(|person| person.name).collect() };
+    assert_eq!(names(people.page(0, 2).await.unwrap()), vec!["Ann", "Bob"]);
+    assert_eq!(names(people.page(4, 2).await.unwrap()), vec!["Ed"]);
+    assert!(people.page(5, 2).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_numeric_ids() {
+    let people = people("numbers").await;
+    let upserted = people.upsert(7, person("Seven", 7)).await.unwrap();
+    assert_eq!(upserted.id.clone().unwrap().to_string(), "person:7");
+    assert_eq!(people.get_by_id(7).await.unwrap(), Some(upserted.clone()));
+    assert_eq!(people.get_by_id("7").await.unwrap(), None);
+    assert_eq!(people.delete(7).await.unwrap(), Some(upserted));
+}
@@ -0,0 +1,131 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::ManageConnection;
+use bb8_surrealdb2::errors::{DatabaseConnectionErrors, ErrorKind};
+use bb8_surrealdb2::retry::Backoff;
+use bb8_surrealdb2::ConnectionManager;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_delay_doubles_within_jitter_and_cap() {
+    let backoff = Backoff {
+        max_retries: 10,
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1000),
+    };
+    for (retry, full) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000), (9, 1000)] {
+        let full = Duration::from_millis(full);
+        for _ in 0..20 {
+            let delay = backoff.delay(retry);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_config_errors_are_not_retried() {
+    let settings = common::memory_settings("");
+    let manager = ConnectionManager::new(settings).with_backoff(Backoff {
+        max_retries: 5,
+        initial_delay: Duration::from_secs(10),
+        max_delay: Duration::from_secs(10),
+    });
+    let started = Instant::now();
+    let error = manager.connect().await.err().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(matches!(
+        error.error,
+        DatabaseConnectionErrors::InvalidNamespace
+    ));
+    assert_eq!(error.kind(), ErrorKind::Config);
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_kinds() {
+    let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+    let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+    let rejected = surrealdb::Error::Db(surrealdb::err::Error::InvalidAuth);
+    let cases = [
+        (
+            DatabaseConnectionErrors::InvalidPort("80a".to_string()),
+            ErrorKind::Config,
+        ),
+        (
+            DatabaseConnectionErrors::ConnectFailed(Box::new(surrealdb::Error::Db(refused.into()))),
+            ErrorKind::Network,
+        ),
+        (
+            DatabaseConnectionErrors::ConnectFailed(Box::new(surrealdb::Error::Db(
+                timed_out.into(),
+            ))),
+            ErrorKind::Timeout,
+        ),
+        (
+            DatabaseConnectionErrors::AuthenticationFailed(Box::new(rejected)),
+            ErrorKind::Auth,
+        ),
+        (
+            DatabaseConnectionErrors::UnreachableEndpoints(vec!["127.0.0.1:2379".to_string()]),
+            ErrorKind::Network,
+        ),
+    ];
+    for (error, kind) in cases {
+        assert_eq!(error.kind(), kind, "{:?}", error);
+        assert_eq!(
+            error.is_retryable(),
+            matches!(kind, ErrorKind::Network | ErrorKind::Timeout)
+        );
+    }
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_refused_connection_is_retried() {
+    use bb8_surrealdb2::DatabaseType;
+    use common::remote_settings;
+
+    // Reserve a free port and release it so that nothing is listening on it.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let settings = remote_settings(DatabaseType::WebSocket, port);
+    let manager = ConnectionManager::new(settings).with_backoff(Backoff {
+        max_retries: 2,
+        initial_delay: Duration::from_millis(200),
+        max_delay: Duration::from_secs(1),
+    });
+    let started = Instant::now();
+    let error = manager.connect().await.err().unwrap();
+    // Waits at least half of 200ms and then half of 400ms.
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(error.kind(), ErrorKind::Network);
+    assert!(error.is_retryable());
+}
+
+#[cfg(feature = "kv-websocket")]
+#[tokio::test]
+async fn test_build_pool_reports_rejected_credentials_without_waiting() {
+    use bb8_surrealdb2::auth::Authentication;
+    use bb8_surrealdb2::{build_pool, DatabaseSettings};
+    use common::{stand_in_settings, StandIn, ROOT_USER};
+
+    let server = StandIn::ws().await;
+    let started = Instant::now();
+    let result = build_pool(DatabaseSettings {
+        auth: Authentication::root(ROOT_USER, "wrong"),
+        ..stand_in_settings(&server)
+    })
+    .await;
+    match result {
+        Err(error) => assert_eq!(error.kind(), ErrorKind::Auth, "{:?}", error),
+        Ok(_) => panic!("Expected the credentials to be rejected"),
+    }
+    // Well within the 10 second connection timeout of the remote pool defaults.
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "kv-rocksdb")]
+extern crate bb8;
+extern crate tokio;
+
+use bb8::Pool;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings};
+
+#[tokio::test]
+async fn test_five_connections_share_one_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = DatabaseSettings::new_file_db(
+        dir.path().to_str().unwrap().to_string(),
+        "test".to_string(),
+        "test".to_string(),
+    );
+    let pool = Pool::builder()
+        .max_size(5)
+        .min_idle(Some(5))
+        .build(ConnectionManager::new(settings))
+        .await
+        .unwrap();
+
+    // Hold every connection at once so that they are all distinct pool entries.
+    let mut connections = Vec::new();
+    for _ in 0..5 {
+        connections.push(pool.get().await.unwrap());
+    }
+    assert_eq!(pool.state().connections, 5);
+    for (index, connection) in connections.iter().enumerate() {
+        connection
+            .query("CREATE type::thing('person', $index) SET index = $index")
+            .bind(("index", index as i64))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+    }
+    for connection in &connections {
+        let mut response = connection
+            .query("SELECT VALUE index FROM person ORDER BY index")
+            .await
+            .unwrap();
+        let indexes: Vec<i64> = response.take(0).unwrap();
+        assert_eq!(indexes, vec![0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,118 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::Pool;
+use bb8_surrealdb2::session::SessionReset;
+use bb8_surrealdb2::ConnectionManager;
+use common::memory_settings;
+
+/// A manager with the default reset, which embedded datastores use like remote servers.
+fn manager(namespace: &str) -> ConnectionManager {
+    ConnectionManager::new(memory_settings(namespace))
+}
+
+async fn single_connection_pool(manager: ConnectionManager) -> Pool<ConnectionManager> {
+    manager
+        .pool_builder()
+        .max_size(1)
+        .build(manager)
+        .await
+        .unwrap()
+}
+
+async fn query_string(pool: &Pool<ConnectionManager>, query: &str) -> Option<String> {
+    let connection = pool.get().await.unwrap();
+    let mut response = connection.query(query).await.unwrap();
+    response.take(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_parameters_are_unset_for_the_next_caller() {
+    let pool = single_connection_pool(manager("parameters")).await;
+    {
+        let connection = pool.get().await.unwrap();
+        connection.set("user", "alice").await.unwrap();
+        let user: Option<String> = connection
+            .query("RETURN $user")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(user.as_deref(), Some("alice"));
+    }
+    assert_eq!(query_string(&pool, "RETURN $user").await, None);
+}
+
+#[tokio::test]
+async fn test_embedded_connections_have_their_own_session() {
+    let manager = manager("isolated");
+    let pool = manager.pool_builder().max_size(2).build(manager).await.unwrap();
+    let first = pool.get().await.unwrap();
+    let second = pool.get().await.unwrap();
+    first.set("user", "alice").await.unwrap();
+    first.use_ns("other").use_db("other").await.unwrap();
+    let mut response = second
+        .query("RETURN [$user, session::ns()]")
+        .await
+        .unwrap();
+    let seen: Vec<Option<String>> = response.take(0).unwrap();
+    assert_eq!(seen, vec![None, Some("isolated".to_string())]);
+}
+
+#[tokio::test]
+async fn test_namespace_is_selected_for_the_next_caller() {
+    let pool = single_connection_pool(manager("selected")).await;
+    {
+        let connection = pool.get().await.unwrap();
+        connection.use_ns("other").use_db("other").await.unwrap();
+    }
+    assert_eq!(
+        query_string(&pool, "RETURN session::ns()").await.as_deref(),
+        Some("selected")
+    );
+    assert_eq!(
+        query_string(&pool, "RETURN session::db()").await.as_deref(),
+        Some("test")
+    );
+}
+
+#[tokio::test]
+async fn test_on_connect_parameters_are_restored() {
+    let manager = manager("restored").on_connect(|client, settings| {
+        Box::pin(async move {
+            client.set("tenant", settings.namespace.clone()).await?;
+            Ok(())
+        })
+    });
+    let pool = single_connection_pool(manager).await;
+    {
+        let connection = pool.get().await.unwrap();
+        connection.set("tenant", "globex").await.unwrap();
+    }
+    assert_eq!(
+        query_string(&pool, "RETURN $tenant").await.as_deref(),
+        Some("restored")
+    );
+}
+
+#[tokio::test]
+async fn test_session_is_kept_without_a_reset() {
+    let pool =
+        single_connection_pool(manager("kept").with_session_reset(SessionReset::none())).await;
+    {
+        let connection = pool.get().await.unwrap();
+        connection.set("user", "alice").await.unwrap();
+        connection.use_ns("other").await.unwrap();
+    }
+    assert_eq!(
+        query_string(&pool, "RETURN $user").await.as_deref(),
+        Some("alice")
+    );
+    assert_eq!(
+        query_string(&pool, "RETURN session::ns()").await.as_deref(),
+        Some("other")
+    );
+}
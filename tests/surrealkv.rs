@@ -0,0 +1,109 @@
+#![cfg(feature = "kv-surreal")]
+extern crate bb8;
+extern crate tokio;
+
+use bb8::{ManageConnection, Pool};
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::time::sleep;
+
+fn settings(dir: &TempDir, versioned: bool) -> DatabaseSettings {
+    DatabaseSettings::new_surrealkv_db(
+        dir.path().to_str().unwrap().to_string(),
+        "test".to_string(),
+        "test".to_string(),
+        versioned,
+    )
+}
+
+async fn pool(settings: DatabaseSettings) -> Pool<ConnectionManager> {
+    Pool::builder()
+        .max_size(3)
+        .build(ConnectionManager::new(settings))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_pooled_connections_share_the_datastore() {
+    let dir = tempfile::tempdir().unwrap();
+    let pool = pool(settings(&dir, false)).await;
+    // Hold every connection at once so that they are all distinct pool entries.
+    let connections = [
+        pool.get().await.unwrap(),
+        pool.get().await.unwrap(),
+        pool.get().await.unwrap(),
+    ];
+    connections[0]
+        .query("CREATE person:tobie SET name = 'Tobie'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    for connection in &connections {
+        let mut response = connection
+            .query("SELECT VALUE name FROM person:tobie")
+            .await
+            .unwrap();
+        let names: Vec<String> = response.take(0).unwrap();
+        assert_eq!(names, vec!["Tobie".to_string()]);
+    }
+}
+
+#[tokio::test]
+async fn test_versioned_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = settings(&dir, true);
+    assert!(matches!(settings.db_type, DatabaseType::VersionedKeyValue));
+    let pool = pool(settings).await;
+    let writer = pool.get().await.unwrap();
+    let reader = pool.get().await.unwrap();
+
+    writer
+        .query("CREATE person:tobie SET name = 'Tobie'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    sleep(Duration::from_millis(10)).await;
+    let mut response = writer.query("RETURN <string> time::now()").await.unwrap();
+    let before: Option<String> = response.take(0).unwrap();
+    sleep(Duration::from_millis(10)).await;
+    writer
+        .query("UPDATE person:tobie SET name = 'Jaime'")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    let mut response = reader
+        .query("SELECT VALUE name FROM person:tobie VERSION <datetime> $before")
+        .bind(("before", before.unwrap()))
+        .await
+        .unwrap();
+    let names: Vec<String> = response.take(0).unwrap();
+    assert_eq!(names, vec!["Tobie".to_string()]);
+
+    let mut response = reader
+        .query("SELECT VALUE name FROM person:tobie")
+        .await
+        .unwrap();
+    let names: Vec<String> = response.take(0).unwrap();
+    assert_eq!(names, vec!["Jaime".to_string()]);
+}
+
+#[tokio::test]
+async fn test_missing_path() {
+    let settings = DatabaseSettings::new_surrealkv_db(
+        "".to_string(),
+        "test".to_string(),
+        "test".to_string(),
+        false,
+    );
+    match ConnectionManager::new(settings).connect().await {
+        Err(error) => assert!(matches!(error.error, DatabaseConnectionErrors::InvalidPath)),
+        Ok(_) => panic!("Expected a missing path to be rejected"),
+    }
+}
@@ -0,0 +1,203 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::RunError;
+use bb8_surrealdb2::errors::DatabaseConnectionErrors;
+use bb8_surrealdb2::tenant::{Tenant, TenantPool};
+use bb8_surrealdb2::DatabaseSettings;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn tenant_pool() -> TenantPool {
+    TenantPool::new(DatabaseSettings::new_memory_db(
+        "template".to_string(),
+        "template".to_string(),
+    ))
+}
+
+async fn session_namespace(tenants: &TenantPool, namespace: &str, database: &str) -> String {
+    let connection = tenants.get(namespace, database).await.unwrap();
+    let mut response = connection.query("RETURN session::ns()").await.unwrap();
+    let selected: Option<String> = response.take(0).unwrap();
+    selected.unwrap()
+}
+
+#[tokio::test]
+async fn test_connections_use_the_tenant() {
+    let tenants = tenant_pool();
+    assert_eq!(session_namespace(&tenants, "acme", "main").await, "acme");
+    assert_eq!(
+        session_namespace(&tenants, "globex", "main").await,
+        "globex"
+    );
+    // The second checkout for a tenant reuses its pool.
+    assert_eq!(session_namespace(&tenants, "acme", "main").await, "acme");
+    let mut names: Vec<String> = tenants
+        .tenants()
+        .into_iter()
+        .map(|tenant| tenant.namespace)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["acme", "globex"]);
+}
+
+#[tokio::test]
+async fn test_embedded_tenants_have_their_own_sessions() {
+    let tenants = tenant_pool().with_connection_timeout(Duration::from_millis(500));
+    let acme = tenants.get("acme", "main").await.unwrap();
+    // A checkout for another tenant does not wait for the connection of acme to be returned.
+    assert_eq!(
+        session_namespace(&tenants, "globex", "main").await,
+        "globex"
+    );
+    let mut response = acme.query("RETURN session::ns()").await.unwrap();
+    let selected: Option<String> = response.take(0).unwrap();
+    assert_eq!(selected.as_deref(), Some("acme"));
+}
+
+#[tokio::test]
+async fn test_embedded_tenants_share_one_datastore() {
+    let tenants = tenant_pool().with_idle_timeout(Duration::from_millis(50));
+    tenants
+        .get("acme", "main")
+        .await
+        .unwrap()
+        .query("CREATE item:one")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(tenants.evict_idle(), 1);
+    // The data outlives the evicted tenant, as it is kept in the datastore shared by all.
+    let connection = tenants.get("acme", "main").await.unwrap();
+    let mut response = connection
+        .query("SELECT VALUE <string> id FROM item")
+        .await
+        .unwrap();
+    let ids: Vec<String> = response.take(0).unwrap();
+    assert_eq!(ids, vec!["item:one".to_string()]);
+}
+
+#[tokio::test]
+async fn test_idle_tenants_are_evicted() {
+    let tenants = tenant_pool().with_idle_timeout(Duration::from_millis(50));
+    drop(tenants.get("globex", "main").await.unwrap());
+    let held = tenants.get("acme", "main").await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+    // A tenant with a connection checked out is kept.
+    assert_eq!(tenants.evict_idle(), 1);
+    assert_eq!(
+        tenants.tenants(),
+        vec![Tenant {
+            namespace: "acme".to_string(),
+            database_name: "main".to_string(),
+        }]
+    );
+    // The tenant is idle from when its last connection is returned.
+    drop(held);
+    assert_eq!(tenants.evict_idle(), 0);
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(tenants.evict_idle(), 1);
+    assert!(tenants.tenants().is_empty());
+}
+
+#[tokio::test]
+async fn test_invalid_tenant_is_not_cached() {
+    let tenants = tenant_pool();
+    match tenants.get("", "main").await {
+        Err(RunError::User(error)) => assert!(matches!(
+            error.error,
+            DatabaseConnectionErrors::InvalidNamespace
+        )),
+        Err(error) => panic!("Expected an invalid namespace, got {:?}", error),
+        Ok(_) => panic!("Expected an invalid namespace"),
+    }
+    assert!(tenants.tenants().is_empty());
+}
+
+#[tokio::test]
+async fn test_managers_are_configured() {
+    let tenants = tenant_pool().with_manager(|manager| {
+        manager.on_connect(|client, settings| {
+            Box::pin(async move {
+                client.set("tenant", settings.namespace.clone()).await?;
+                Ok(())
+            })
+        })
+    });
+    // The hook runs with the settings of the tenant whose pool opens the connection.
+    for namespace in ["acme", "globex", "acme"] {
+        let connection = tenants.get(namespace, "main").await.unwrap();
+        let mut response = connection.query("RETURN $tenant").await.unwrap();
+        let tenant: Option<String> = response.take(0).unwrap();
+        assert_eq!(tenant.as_deref(), Some(namespace));
+    }
+}
+
+#[cfg(feature = "kv-websocket")]
+mod remote {
+    use super::*;
+    use common::{stand_in_settings, StandIn};
+
+    fn tenant_pool(server: &StandIn) -> TenantPool {
+        TenantPool::new(DatabaseSettings {
+            namespace: "template".to_string(),
+            database_name: "template".to_string(),
+            ..stand_in_settings(server)
+        })
+        .with_max_connections(2)
+        .with_connection_timeout(Duration::from_secs(2))
+    }
+
+    fn namespaces(tenants: &TenantPool) -> Vec<String> {
+        let mut names: Vec<String> = tenants
+            .tenants()
+            .into_iter()
+            .map(|tenant| tenant.namespace)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_tenants_have_their_own_connections() {
+        let server = StandIn::ws().await;
+        let tenants = tenant_pool(&server);
+        let _acme = tenants.get("acme", "main").await.unwrap();
+        // Connections of remote tenants do not share a session, so they can be out at once.
+        assert_eq!(
+            session_namespace(&tenants, "globex", "main").await,
+            "globex"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_count_against_the_limit() {
+        let server = StandIn::ws().await;
+        let tenants = tenant_pool(&server);
+        drop(tenants.get("acme", "main").await.unwrap());
+        drop(tenants.get("globex", "main").await.unwrap());
+        // Both connections are still open, so the least recently used tenant makes room.
+        drop(tenants.get("initech", "main").await.unwrap());
+        assert_eq!(namespaces(&tenants), vec!["globex", "initech"]);
+    }
+
+    #[tokio::test]
+    async fn test_checked_out_connections_are_not_evicted() {
+        let server = StandIn::ws().await;
+        let tenants = tenant_pool(&server);
+        let acme = tenants.get("acme", "main").await.unwrap();
+        let _globex = tenants.get("globex", "main").await.unwrap();
+        assert!(matches!(
+            tenants.get("initech", "main").await,
+            Err(RunError::TimedOut)
+        ));
+        drop(acme);
+        tenants.get("initech", "main").await.unwrap();
+        assert_eq!(namespaces(&tenants), vec!["globex", "initech"]);
+    }
+}
@@ -1,25 +1,17 @@
+#![recursion_limit = "256"]
 extern crate bb8;
-extern crate once_cell;
 extern crate tokio;
 
-use bb8::Pool;
-use bb8_surrealdb2::{ConnectionManager, DatabaseSettings};
-use std::sync::Arc;
-use tokio::sync::OnceCell;
-
-static POOL: OnceCell<Arc<Pool<ConnectionManager>>> = OnceCell::const_new();
+mod common;
 
-async fn init_pool() -> Result<Arc<Pool<ConnectionManager>>, Box<dyn std::error::Error>> {
-    let settings = DatabaseSettings::new_memory_db("test".to_string(), "test".to_string());
-    let manager = ConnectionManager::new(settings);
-    let pool = Pool::builder().max_size(3).build(manager).await?;
-    Ok(Arc::new(pool))
-}
+use bb8::Pool;
+use bb8_surrealdb2::errors::{ConnectionError, DatabaseConnectionErrors};
+use bb8_surrealdb2::{build_pool, global_pool, ConnectionManager, DatabaseSettings};
+use common::memory_settings;
+use std::error::Error;
 
-async fn get_pool() -> Result<Arc<Pool<ConnectionManager>>, Box<dyn std::error::Error>> {
-    POOL.get_or_try_init(init_pool)
-        .await
-        .map(|pool| pool.clone())
+async fn get_pool() -> Result<&'static Pool<ConnectionManager>, ConnectionError> {
+    global_pool(|| async { Ok(memory_settings("test")) }).await
 }
 
 /// Allows running multiple tests in parallel to demonstrate the connection pool use.
@@ -47,11 +39,136 @@ mod tests {
                         println!("Version={}", version);
                         assert_ne!(version.to_string(), "");
                     }
-                    Err(e) => assert!(false, "Failed to get version: {:?}", e),
+                    Err(e) => panic!("Failed to get version: {:?}", e),
                 },
-                Err(e) => assert!(false, "Failed to get connection: {:?}", e),
+                Err(e) => panic!("Failed to get connection: {:?}", e),
             },
-            Err(e) => assert!(false, "Failed to initialize pool: {:?}", e),
+            Err(e) => panic!("Failed to initialize pool: {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_connections_share_data() {
+        let pool = get_pool().await.unwrap();
+        // Hold two connections at once so that they are guaranteed to be distinct pool entries.
+        let writer = pool.get().await.unwrap();
+        let reader = pool.get().await.unwrap();
+        writer
+            .query("CREATE shared:one SET name = 'shared'")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let mut response = reader
+            .query("SELECT VALUE name FROM shared:one")
+            .await
+            .unwrap();
+        let names: Vec<String> = response.take(0).unwrap();
+        assert_eq!(names, vec!["shared".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_connections_have_their_own_sessions() {
+        let pool = build_pool(memory_settings("sessions")).await.unwrap();
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        first.set("owner", "first").await.unwrap();
+        second.use_ns("other").await.unwrap();
+
+        let owner: Option<String> = second
+            .query("RETURN $owner")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(owner, None);
+        let owner: Option<String> = first.query("RETURN $owner").await.unwrap().take(0).unwrap();
+        assert_eq!(owner.as_deref(), Some("first"));
+        let namespace: Option<String> = first
+            .query("RETURN session::ns()")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(namespace.as_deref(), Some("sessions"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_embedded_open_can_be_retried() {
+        use bb8::ManageConnection;
+        use std::time::Duration;
+
+        let manager = ConnectionManager::new(memory_settings("cancelled"));
+        // Cancelled while the datastore is being opened.
+        assert!(tokio::time::timeout(Duration::ZERO, manager.connect())
+            .await
+            .is_err());
+        manager.connect().await.unwrap().health().await.unwrap();
+    }
+
+    #[test]
+    fn test_connection_error_source_chain() {
+        let cause = surrealdb::Error::Db(surrealdb::err::Error::Thrown("refused".to_string()));
+        let error = ConnectionError::from(DatabaseConnectionErrors::ConnectFailed(Box::new(cause)));
+        assert_eq!(error.to_string(), "ConnectionError: Cannot connect");
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            "DatabaseConnectionError: Database connect error"
+        );
+        assert!(source.source().unwrap().to_string().contains("refused"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_error_context() {
+        use bb8::ManageConnection;
+
+        let settings = DatabaseSettings::new_memory_db("app".to_string(), "".to_string());
+        let error = match ConnectionManager::new(settings).connect().await {
+            Err(error) => error,
+            Ok(_) => panic!("Expected the empty database name to be rejected"),
+        };
+        assert!(matches!(
+            error.error,
+            DatabaseConnectionErrors::InvalidDatabaseName
+        ));
+        let context = error.context.as_ref().unwrap();
+        assert_eq!(context.engine, "mem");
+        assert_eq!(context.namespace, "app");
+        assert_eq!(
+            error.to_string(),
+            "ConnectionError: Cannot connect to mem:// namespace `app` database ``"
+        );
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "DatabaseConnectionError: Database Name not set error"
+        );
+    }
+
+    #[cfg(feature = "kv-websocket")]
+    #[tokio::test]
+    async fn test_refused_websocket_returns_error() {
+        use bb8::ManageConnection;
+        use bb8_surrealdb2::DatabaseType;
+        use common::remote_settings;
+
+        // Reserve a free port and release it so that nothing is listening on it.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let manager = ConnectionManager::new(remote_settings(DatabaseType::WebSocket, port));
+        match manager.connect().await {
+            Err(ConnectionError {
+                error: DatabaseConnectionErrors::ConnectFailed(_),
+                ..
+            }) => {}
+            other => panic!("Expected a connect failure, got {:?}", other.err()),
         }
+        let error = manager.connect().await.err().unwrap();
+        assert_eq!(
+            error.context.unwrap().address,
+            format!("127.0.0.1:{}", port)
+        );
     }
 }
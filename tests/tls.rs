@@ -0,0 +1,123 @@
+#![cfg(feature = "kv-websocket")]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::{ManageConnection, Pool};
+use bb8_surrealdb2::errors::{ConnectionError, DatabaseConnectionErrors};
+use bb8_surrealdb2::tls::TlsSettings;
+use bb8_surrealdb2::{ConnectionManager, DatabaseSettings, DatabaseType};
+use common::{remote_settings, Protocol, StandIn, TlsMaterial};
+
+fn settings(db_type: DatabaseType, port: u16, tls: Option<TlsSettings>) -> DatabaseSettings {
+    DatabaseSettings {
+        host: "localhost".to_string(),
+        tls,
+        ..remote_settings(db_type, port)
+    }
+}
+
+fn trusted(material: &TlsMaterial) -> TlsSettings {
+    TlsSettings {
+        ca_bundle: Some(material.ca_bundle()),
+        ..Default::default()
+    }
+}
+
+async fn assert_round_trip(manager: ConnectionManager) {
+    let pool = Pool::builder().max_size(2).build(manager).await.unwrap();
+    let connection = pool.get().await.unwrap();
+    let mut response = connection
+        .query("RETURN [session::ns(), session::db()]")
+        .await
+        .unwrap();
+    let selected: Vec<String> = response.take(0).unwrap();
+    assert_eq!(selected, vec!["test".to_string(), "test".to_string()]);
+}
+
+async fn assert_connect_error(manager: ConnectionManager, expected: &str) {
+    match manager.connect().await {
+        Err(ConnectionError { error, .. }) => assert_eq!(error.as_str(), expected),
+        Ok(_) => panic!("Expected the connection to fail"),
+    }
+}
+
+#[tokio::test]
+async fn test_wss_with_ca_bundle() {
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::WebSocket, Some(material.server_config(false))).await;
+    let manager = ConnectionManager::new(settings(
+        DatabaseType::WebSocketSecure,
+        server.port(),
+        Some(trusted(&material)),
+    ));
+    assert_round_trip(manager).await;
+}
+
+#[tokio::test]
+async fn test_wss_rejects_untrusted_certificate() {
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::WebSocket, Some(material.server_config(false))).await;
+    let manager =
+        ConnectionManager::new(settings(DatabaseType::WebSocketSecure, server.port(), None));
+    assert_connect_error(manager, "Database connect error").await;
+}
+
+#[tokio::test]
+async fn test_wss_danger_accept_invalid_certificates() {
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::WebSocket, Some(material.server_config(false))).await;
+    let tls = TlsSettings {
+        danger_accept_invalid_certificates: true,
+        ..Default::default()
+    };
+    let manager = ConnectionManager::new(settings(
+        DatabaseType::WebSocketSecure,
+        server.port(),
+        Some(tls),
+    ));
+    assert_round_trip(manager).await;
+}
+
+#[tokio::test]
+async fn test_wss_client_certificate() {
+    let material = TlsMaterial::generate();
+    let server = StandIn::start(Protocol::WebSocket, Some(material.server_config(true))).await;
+
+    let manager = ConnectionManager::new(settings(
+        DatabaseType::WebSocketSecure,
+        server.port(),
+        Some(trusted(&material)),
+    ));
+    assert_connect_error(manager, "Database connect error").await;
+
+    let tls = TlsSettings {
+        client_certificate: Some(material.client_certificate()),
+        client_key: Some(material.client_key()),
+        ..trusted(&material)
+    };
+    let manager = ConnectionManager::new(settings(
+        DatabaseType::WebSocketSecure,
+        server.port(),
+        Some(tls),
+    ));
+    assert_round_trip(manager).await;
+}
+
+#[tokio::test]
+async fn test_client_certificate_without_key_is_rejected() {
+    let material = TlsMaterial::generate();
+    let tls = TlsSettings {
+        client_certificate: Some(material.client_certificate()),
+        ..trusted(&material)
+    };
+    let manager = ConnectionManager::new(settings(DatabaseType::WebSocketSecure, 8000, Some(tls)));
+    match manager.connect().await {
+        Err(ConnectionError {
+            error: DatabaseConnectionErrors::InvalidTlsConfiguration(_),
+            ..
+        }) => {}
+        other => panic!("Expected a TLS configuration error, got {:?}", other.err()),
+    }
+}
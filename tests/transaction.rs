@@ -0,0 +1,151 @@
+#![recursion_limit = "256"]
+extern crate bb8;
+extern crate tokio;
+
+mod common;
+
+use bb8::Pool;
+use bb8_surrealdb2::errors::TransactionError;
+use bb8_surrealdb2::retry::Backoff;
+use bb8_surrealdb2::transaction::PoolTransaction;
+use bb8_surrealdb2::{build_pool, ConnectionManager};
+use common::memory_settings;
+use futures::FutureExt;
+use std::convert::Infallible;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
+async fn pool(namespace: &str) -> Pool<ConnectionManager> {
+    build_pool(memory_settings(namespace)).await.unwrap()
+}
+
+async fn count(pool: &Pool<ConnectionManager>, table: &str) -> usize {
+    let connection = pool.get().await.unwrap();
+    let mut response = connection
+        .query("SELECT VALUE id FROM type::table($table)")
+        .bind(("table", table.to_string()))
+        .await
+        .unwrap();
+    let ids: Vec<surrealdb::sql::Thing> = response.take(0).unwrap();
+    ids.len()
+}
+
+#[tokio::test]
+async fn test_statements_are_committed_together() {
+    let pool = pool("committed").await;
+    let (value, mut response) = pool
+        .transaction(|tx| async move {
+            tx.query("CREATE item:one SET name = $name")
+                .query("CREATE item:two SET name = 'second'")
+                .bind("name", "first");
+            Ok::<_, Infallible>("built")
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, "built");
+    let names: Vec<String> = response.take((0, "name")).unwrap();
+    assert_eq!(names, vec!["first".to_string()]);
+    assert_eq!(count(&pool, "item").await, 2);
+}
+
+#[tokio::test]
+async fn test_aborted_transaction_is_not_sent() {
+    let pool = pool("aborted").await;
+    let result = pool
+        .transaction(|tx| async move {
+            tx.query("CREATE item:one");
+            Err::<(), _>("the input is invalid")
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionError::Aborted("the input is invalid"))
+    ));
+    assert_eq!(count(&pool, "item").await, 0);
+}
+
+#[tokio::test]
+async fn test_panicking_transaction_is_not_sent() {
+    let manager = ConnectionManager::new(memory_settings("panicked"));
+    let pool = manager
+        .pool_builder()
+        .max_size(2)
+        .connection_timeout(Duration::from_secs(1))
+        .build(manager)
+        .await
+        .unwrap();
+    let result = AssertUnwindSafe(pool.transaction(|tx| async move {
+        tx.query("CREATE item:one SET amount = $amount")
+            .bind("amount", "ten".parse::<i64>().expect("the amount is a number"));
+        Ok::<_, Infallible>(())
+    }))
+    .catch_unwind()
+    .await;
+    assert!(result.is_err());
+    // The connection is not inside a transaction, so its write is seen from the other one.
+    let connection = pool.get().await.unwrap();
+    connection
+        .query("CREATE item:two")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    let mut response = pool
+        .get()
+        .await
+        .unwrap()
+        .query("SELECT VALUE <string> id FROM item")
+        .await
+        .unwrap();
+    let ids: Vec<String> = response.take(0).unwrap();
+    assert_eq!(ids, vec!["item:two".to_string()]);
+}
+
+#[tokio::test]
+async fn test_failed_statement_rolls_back_the_transaction() {
+    let pool = pool("rolled_back").await;
+    let result = pool
+        .transaction(|tx| async move {
+            tx.query("CREATE item:one").query("THROW 'failed'");
+            Ok::<_, Infallible>(())
+        })
+        .await;
+    assert!(matches!(result, Err(TransactionError::Failed(_))));
+    assert_eq!(count(&pool, "item").await, 0);
+}
+
+#[tokio::test]
+async fn test_concurrent_transactions_are_retried() {
+    let pool = pool("concurrent").await;
+    pool.get()
+        .await
+        .unwrap()
+        .query("CREATE counter:one SET value = 0")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    let backoff = Backoff {
+        max_retries: 20,
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(20),
+    };
+    let transactions = (0..8).map(|_| {
+        pool.transaction_with(&backoff, |tx| async move {
+            tx.query("UPDATE counter:one SET value += 1");
+            Ok::<_, Infallible>(())
+        })
+    });
+    for result in futures::future::join_all(transactions).await {
+        result.unwrap();
+    }
+    let mut response = pool
+        .get()
+        .await
+        .unwrap()
+        .query("RETURN counter:one.value")
+        .await
+        .unwrap();
+    let value: Option<i64> = response.take(0).unwrap();
+    assert_eq!(value, Some(8));
+}